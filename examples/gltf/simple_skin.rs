@@ -3,7 +3,9 @@ use std::sync::{mpsc::channel, Arc};
 use default_render:: SingleIDBaseDefaultMaterial;
 use pi_3d::PluginBundleDefault;
 use pi_3d_loader::{
-    factory::{GltfLoader, ActionListGLTFLoaded, sys_gltf_decode, OpsGLTFLoaded},
+    factory::{GltfLoader, ActionListGLTFLoaded, OpsGLTFLoaded},
+    texture::{split_ktx2_target, GLTFEmbeddedImages},
+    PluginGLTFLoader, PluginGLTFParticle,
 };
 // use pi_ecs::prelude::Component;
 use pi_async::rt::AsyncRuntime;
@@ -21,11 +23,18 @@ use unlit_material::PluginUnlitMaterial;
 pub struct PluginLocalLoad;
 impl Plugin for PluginLocalLoad {
     fn build(&self, app: &mut App) {
-        init_load_cb(Arc::new(|path: String| {
+        let images = GLTFEmbeddedImages::default();
+        app.insert_resource(images.clone());
+        init_load_cb(Arc::new(move |path: String| {
+            let images = images.clone();
             MULTI_MEDIA_RUNTIME
                 .spawn(MULTI_MEDIA_RUNTIME.alloc(), async move {
                     log::warn!("Load {}", path);
-                    if let Ok(r) = std::fs::read(path.clone()) {
+                    // KTX2 纹理 key 带有转码目标, 读取文件时去掉
+                    let (file, _target) = split_ktx2_target(&path);
+                    if let Some(r) = images.take(file) {
+                        on_load(&path, r);
+                    } else if let Ok(r) = std::fs::read(file) {
                         on_load(&path, r);
                     } else {
                         log::error!("Load Error: {:?}", path);
@@ -247,10 +256,8 @@ impl Plugin for PluginTest {
         app.insert_resource(ActionListTestData::default());
         app.add_frame_event::<ComponentEvent<Changed<Layer>>>();
 
-        app.insert_resource(ActionListGLTFLoaded::default());
-        app.add_system(
-            sys_gltf_decode.in_set(ERunStageChap::Command)
-        );
+        PluginGLTFLoader.build(app);
+        PluginGLTFParticle.build(app);
 
         // PluginQuadBuilder.init(engine, stages);
         PluginSkeleton.build(app);
@@ -1,14 +1,24 @@
-use crate::interface::GLTFAPI;
+use crate::{
+    extras::{
+        shader::{
+            BlockDistortionUV, BlockOpacity2Texture, BlockOpacity2TextureUVOffsetSpeed,
+            BlockTwoOpacityMix,
+        },
+        uniform::ExtrasUniform,
+    },
+    interface::{GLTFMaterialState, GLTFAPI},
+    texture::{gltf_texture_param, GLTFTextureTransform},
+};
 use std::path::Path;
 
 use bevy::prelude::Entity;
 use pi_atom::Atom;
 use pi_engine_shell::prelude::*;
-use pi_gltf::{image, json::Value};
+use pi_gltf::json::Value;
 use pi_node_materials::prelude::{
     BlockCutoff, BlockEmissiveTexture, BlockEmissiveTextureUVOffsetSpeed, BlockMainTexture,
-    BlockMainTextureUVOffsetSpeed, BlockMaskTexture, BlockMaskTextureUVOffsetSpeed, BlockOpacity,
-    BlockOpacityFresnel, BlockOpacityTexture, BlockOpacityTextureUVOffsetSpeed,
+    BlockMainTextureUVOffsetSpeed, BlockMaskTexture, BlockOpacity, BlockOpacityFresnel,
+    BlockOpacityTexture, BlockOpacityTextureUVOffsetSpeed,
 };
 
 use pi_scene_context::prelude::*;
@@ -17,6 +27,7 @@ use unlit_material::{
     effects::{
         distortion_uv::DistortionUVShader,
         main_opacity::MainOpacityShader, main_opacity_fresnel::MainOpacityFresnelShader,
+        two_opacity_mix::TwoOpacityMixShader,
     },
 };
 
@@ -25,44 +36,214 @@ const DISTORTION_UV: &'static str = "distortionUV";
 const TWO_OPACITY_MIX: &'static str = "two_opacity_mix";
 const MAIN_OPACITY_OPACITY_FRESNEL: &'static str = "main_opacity_opacity_fresnel";
 
+const MAIN_OPACITY_UNIFORMS: &[ExtrasUniform] = &[
+    ExtrasUniform::vec4(&["emissionColor"], BlockEmissiveTexture::KEY_INFO),
+    ExtrasUniform::float(&["alpha"], BlockOpacity::KEY_ALPHA),
+    ExtrasUniform::float(&["alphaCutOff"], BlockCutoff::KEY_VALUE),
+];
+
+const MAIN_OPACITY_OPACITY_FRESNEL_UNIFORMS: &[ExtrasUniform] = &[
+    ExtrasUniform::vec4(&["emissionColor"], BlockEmissiveTexture::KEY_INFO),
+    ExtrasUniform::float(&["alpha"], BlockOpacity::KEY_ALPHA),
+    ExtrasUniform::float(&["alphaCutOff"], BlockCutoff::KEY_VALUE),
+    ExtrasUniform::vec2(&["OFBias", "OFPower"], BlockOpacityFresnel::KEY_PARAM),
+    ExtrasUniform::vec4(&["OFLeft"], BlockOpacityFresnel::KEY_LEFT),
+    ExtrasUniform::vec4(&["OFRight"], BlockOpacityFresnel::KEY_RIGHT),
+];
+
+const DISTORTION_UV_UNIFORMS: &[ExtrasUniform] = &[
+    ExtrasUniform::float(&["alpha"], BlockOpacity::KEY_ALPHA),
+    ExtrasUniform::float(&["alphaCutOff"], BlockCutoff::KEY_VALUE),
+    // xy: 扭曲强度, zw: 扭曲流动速度
+    ExtrasUniform::vec4(
+        &["distortionX", "distortionY", "distortionSX", "distortionSY"],
+        BlockDistortionUV::KEY_DISTORTION,
+    )
+    .with_default([0., 0., 0., 0.]),
+    ExtrasUniform::float(&["maskFlowMode"], DistortionUVShader::KEY_MODE),
+    ExtrasUniform::float(
+        &["vertexColorFactor"],
+        BlockDistortionUV::KEY_VERTEX_COLOR_FACTOR,
+    ),
+    // 编辑器导出为 bool 或 0/1
+    ExtrasUniform::float(
+        &["opacityFromMaskBlue"],
+        BlockDistortionUV::KEY_OPACITY_FROM_MASK_BLUE,
+    ),
+];
+
+const TWO_OPACITY_MIX_UNIFORMS: &[ExtrasUniform] = &[
+    ExtrasUniform::float(&["mixControl"], BlockTwoOpacityMix::KEY_MIX_CONTROL),
+    ExtrasUniform::float(&["alpha"], BlockOpacity::KEY_ALPHA),
+    ExtrasUniform::float(&["alphaCutOff"], BlockCutoff::KEY_VALUE),
+];
+
+// diffuseOU / diffuseOV 为流动一个周期的毫秒数
+const DIFFUSE_UNIFORMS: &[ExtrasUniform] = &[
+    ExtrasUniform::float(&["diffuseLevel"], BlockMainTexture::KEY_LEVEL),
+    ExtrasUniform::vec2(
+        &["diffuseOU", "diffuseOV"],
+        BlockMainTextureUVOffsetSpeed::KEY_PARAM,
+    )
+    .reciprocal(1000.),
+];
+
+const DIFFUSE_TEXTURE_UNIFORMS: &[ExtrasUniform] = &[ExtrasUniform::vec4(
+    &["scale", "offset"],
+    BlockMainTexture::KEY_TILLOFF,
+)];
+
+const EMISSIVE_UNIFORMS: &[ExtrasUniform] = &[
+    ExtrasUniform::float(&["emissiveMapLevel"], BlockEmissiveTexture::KEY_LEVEL),
+    ExtrasUniform::vec2(
+        &["emissionOU", "emissionOV"],
+        BlockEmissiveTextureUVOffsetSpeed::KEY_PARAM,
+    ),
+];
+
+const EMISSIVE_TEXTURE_UNIFORMS: &[ExtrasUniform] = &[ExtrasUniform::vec4(
+    &["scale", "offset"],
+    BlockEmissiveTexture::KEY_TILLOFF,
+)];
+
+const OPACITY_UNIFORMS: &[ExtrasUniform] = &[
+    ExtrasUniform::float(&["opacityLevel"], BlockOpacityTexture::KEY_LEVEL),
+    ExtrasUniform::vec2(
+        &["opacityOU", "opacityOV"],
+        BlockOpacityTextureUVOffsetSpeed::KEY_PARAM,
+    ),
+];
+
+const OPACITY_TEXTURE_UNIFORMS: &[ExtrasUniform] = &[ExtrasUniform::vec4(
+    &["scale", "offset"],
+    BlockOpacityTexture::KEY_TILLOFF,
+)];
+
+const OPACITY2_UNIFORMS: &[ExtrasUniform] = &[
+    ExtrasUniform::float(&["opacity2Level"], BlockOpacity2Texture::KEY_LEVEL),
+    ExtrasUniform::vec2(
+        &["opacity2OU", "opacity2OV"],
+        BlockOpacity2TextureUVOffsetSpeed::KEY_PARAM,
+    ),
+];
+
+const OPACITY2_TEXTURE_UNIFORMS: &[ExtrasUniform] = &[ExtrasUniform::vec4(
+    &["scale", "offset"],
+    BlockOpacity2Texture::KEY_TILLOFF,
+)];
+
+const MASK_TEXTURE_UNIFORMS: &[ExtrasUniform] = &[ExtrasUniform::vec4(
+    &["scale", "offset"],
+    BlockMaskTexture::KEY_TILLOFF,
+)];
+
+/// extras 材质处理函数: (api, 材质, extras[key] 的配置, textures, gltf 路径)
+///   * 只设置材质自身的数据, 同一材质会被多个 mesh 共用
+///   * mesh 的渲染状态 (cull / zWrite / zTest / alphaMode / renderQueue) 由 gltf_apply_material 统一设置
+pub type ExtrasMaterialHandler =
+    fn(&mut GLTFAPI<'_, '_>, Entity, &Value, &Vec<pi_gltf::Texture>, &Path);
+
+/// extras 材质注册表
+///   * 按注册顺序匹配 extras 中的 key, 先注册的优先
+///   * 其他 crate 通过 regist 添加新的材质类型, 相同 key 会替换原处理函数
+#[derive(Resource)]
+pub struct GLTFExtrasMaterials {
+    handlers: Vec<(String, ExtrasMaterialHandler)>,
+}
+
+impl Default for GLTFExtrasMaterials {
+    fn default() -> Self {
+        let mut result = Self { handlers: vec![] };
+        result.regist(
+            DISTORTION_UV,
+            |api, idmat, info, textures, root_path| {
+                api.distortion_uv(root_path, idmat, info, textures)
+            },
+        );
+        result.regist(
+            MAIN_OPACITY,
+            |api, idmat, info, textures, root_path| {
+                api.main_opacity(root_path, idmat, info, textures)
+            },
+        );
+        result.regist(
+            TWO_OPACITY_MIX,
+            |api, idmat, info, textures, root_path| {
+                api.two_opacity_mix(root_path, idmat, info, textures)
+            },
+        );
+        result.regist(
+            MAIN_OPACITY_OPACITY_FRESNEL,
+            |api, idmat, info, textures, root_path| {
+                api.main_opacity_opacity_fresnel(root_path, idmat, info, textures)
+            },
+        );
+        result
+    }
+}
+
+impl GLTFExtrasMaterials {
+    pub fn regist(&mut self, key: &str, handler: ExtrasMaterialHandler) {
+        if let Some(item) = self.handlers.iter_mut().find(|(k, _)| k == key) {
+            item.1 = handler;
+        } else {
+            self.handlers.push((String::from(key), handler));
+        }
+    }
+
+    pub fn contains(&self, key: &str) -> bool {
+        self.handlers.iter().any(|(k, _)| k == key)
+    }
+
+    /// 返回匹配到的 key, 配置与处理函数
+    pub fn find<'s, 'v>(
+        &'s self,
+        extras: &'v Value,
+    ) -> Option<(&'s str, &'v Value, ExtrasMaterialHandler)> {
+        self.handlers.iter().find_map(|(key, handler)| {
+            extras.get(key).map(|info| (key.as_str(), info, *handler))
+        })
+    }
+}
+
 impl GLTFAPI<'_, '_> {
+    /// 创建 extras 材质, 返回匹配到的 key
+    /// 没有匹配的 extras 材质时返回 None, 由调用方回退到其他材质
+    /// glTF 核心属性 (alphaCutoff, emissive) 先写入, extras 中的配置 (alphaCutOff, emissionColor) 覆盖之
     pub fn gltf_extras_material(
         &mut self,
-        entity: Entity,
         idmat: Entity,
+        material: &pi_gltf::Material,
+        state: &GLTFMaterialState,
         extras: &Value,
         textures: &Vec<pi_gltf::Texture>,
         root_path: &Path,
-    ) -> ObjectID {
-        if let Some(distortion_uv) = extras.get(DISTORTION_UV) {
-            self.distortion_uv(root_path, entity, idmat, distortion_uv, textures);
-        } else if let Some(main_opacity) = extras.get(MAIN_OPACITY) {
-            self.main_opacity(root_path, entity, idmat, main_opacity, textures);
-        } else if let Some(_two_opacity_mix) = extras.get(TWO_OPACITY_MIX) {
-            todo!()
-        } else if let Some(main_opacity_opacity_fresnel) = extras.get(MAIN_OPACITY_OPACITY_FRESNEL)
-        {
-            self.main_opacity_opacity_fresnel(
-                root_path,
-                entity,
-                idmat,
-                main_opacity_opacity_fresnel,
-                textures,
-            )
-        }
+    ) -> Option<String> {
+        match self.commands.extras_materials.find(extras) {
+            Some((key, info, handler)) => {
+                let key = String::from(key);
+                self.gltf_material_cutoff(idmat, state);
+                self.gltf_material_emissive(idmat, material, root_path);
 
-        self.commands
-            .matcmds
-            .usemat
-            .push(OpsMaterialUse::ops(entity, idmat));
+                handler(self, idmat, info, textures, root_path);
 
-        idmat
+                Some(key)
+            }
+            None => {
+                if let Some(keys) = extras.as_object().filter(|v| !v.is_empty()) {
+                    log::warn!(
+                        "extras material is not registed: {:?}",
+                        keys.keys().collect::<Vec<_>>()
+                    );
+                }
+                None
+            }
+        }
     }
 
     fn distortion_uv(
         &mut self,
         root_path: &Path,
-        entity: Entity,
         idmat: Entity,
         distortion_uv: &Value,
         textures: &Vec<pi_gltf::Texture>,
@@ -81,152 +262,21 @@ impl GLTFAPI<'_, '_> {
         ));
 
         if let Some(diffuse_texture) = distortion_uv.get("diffuseTexture") {
-            println!("diffuse_texture: {:?}", diffuse_texture);
             self.diffuse_texture(root_path, idmat, distortion_uv, diffuse_texture, textures)
         }
 
         if let Some(mask_texture) = distortion_uv.get("maskTexture") {
-            println!("mask_texture: {:?}", mask_texture);
             self.mask_texture(root_path, idmat, mask_texture, textures)
         }
 
-        if let Some(_diffuse_color) = distortion_uv.get("diffuseColor") {
-            self.commands.matcmds.vec4.push(OpsUniformVec4::ops(
-                idmat,
-                Atom::from(BlockMainTexture::KEY_COLOR),
-                1.,
-                1.,
-                1.,
-                1.,
-            ));
-        }
-
-        if let Some(alpha) = distortion_uv.get("alpha") {
-            self.commands.matcmds.float.push(OpsUniformFloat::ops(
-                idmat,
-                Atom::from(BlockOpacity::KEY_ALPHA),
-                alpha.as_f64().unwrap() as f32,
-            ));
-        }
-
-        if let Some(alpha_cut_off) = distortion_uv.get("alphaCutOff") {
-            self.commands.matcmds.float.push(OpsUniformFloat::ops(
-                idmat,
-                Atom::from(BlockCutoff::KEY_VALUE),
-                alpha_cut_off.as_f64().unwrap() as f32,
-            ));
-        }
-
-        if let Some(_distortion_x) = distortion_uv.get("distortionX") {
-            // self.commands.matcmds.float.push(OpsUniformFloat::ops(
-            //     idmat,
-            //     Atom::from("distortionX"),
-            //     distortion_x.as_f64().unwrap() as f32,
-            // ));
-        }
-
-        if let Some(_distortion_y) = distortion_uv.get("distortionY") {
-            // self.commands.matcmds.float.push(OpsUniformFloat::ops(
-            //     idmat,
-            //     Atom::from("distortionY"),
-            //     distortion_y.as_f64().unwrap() as f32,
-            // ));
-        }
-
-        if let Some(_distortion_sx) = distortion_uv.get("distortionSX") {
-            // self.commands.matcmds.float.push(OpsUniformFloat::ops(
-            //     idmat,
-            //     Atom::from("distortionSX"),
-            //     distortion_sx.as_f64().unwrap() as f32,
-            // ));
-        }
-
-        if let Some(_distortion_sy) = distortion_uv.get("distortionSY") {
-            // self.commands.matcmds.float.push(OpsUniformFloat::ops(
-            //     idmat,
-            //     Atom::from("distortionSY"),
-            //     distortion_sy.as_f64().unwrap() as f32,
-            // ));
-        }
-
-        if let Some(mask_flow_mode) = distortion_uv.get("maskFlowMode") {
-            self.commands.matcmds.float.push(OpsUniformFloat::ops(
-                idmat,
-                Atom::from(DistortionUVShader::KEY_MODE),
-                mask_flow_mode.as_f64().unwrap() as f32,
-            ));
-        }
-
-        if let Some(_vertex_color_factor) = distortion_uv.get("vertexColorFactor") {
-            // self.commands.matcmds.float.push(OpsUniformFloat::ops(
-            //     idmat,
-            //     Atom::from("vertexColorFactor"),
-            //     vertex_color_factor.as_f64().unwrap() as f32,
-            // ));
-        }
-
-        if let Some(_opacity_from_mask_blue) = distortion_uv.get("opacityFromMaskBlue") {
-            // self.commands.matcmds.float.push(OpsUniformFloat::ops(
-            //     idmat,
-            //     Atom::from("opacityFromMaskBlue"),
-            //     opacity_from_mask_blue.as_f64().unwrap() as f32,
-            // ));
-        }
-
-        let mut cull_mode = CullMode::Off;
-        if let Some(cull) = distortion_uv.get("cull") {
-            let cull = cull.as_str().unwrap();
-            if cull == "off" {
-                cull_mode = CullMode::Off
-            } else if cull == "front" {
-                cull_mode = CullMode::Front
-            }
-        }
-
-        // TODO:????
-        self.commands
-            .meshcmds
-            .cullmode
-            .push(OpsCullMode::ops(entity, cull_mode));
-
-        let _depth_test = true;
+        self.diffuse_color(idmat, distortion_uv);
 
-        let mut depth_write = false;
-        if let Some(z_write) = distortion_uv.get("zWrite") {
-            if z_write.as_bool().unwrap() {
-                depth_write = true;
-            }
-        }
-        self.commands
-            .meshcmds
-            .depth_write
-            .push(OpsDepthWrite::ops(entity, depth_write));
-
-        let mut _alpha_mode = 0;
-        if let Some(_t_alpha_mode) = distortion_uv.get("alphaMode") {
-            println!("=========== distortion_uv alphaMode ===========");
-            let mut blend = ModelBlend::default();
-            blend.combine();
-            self.commands
-                .meshcmds
-                .blend
-                .push(OpsRenderBlend::ops(entity, blend));
-        }
-
-        let mut render_queue = 3000;
-        if let Some(t_render_queue) = distortion_uv.get("renderQueue") {
-            render_queue = t_render_queue.as_i64().unwrap();
-        }
-        self.commands
-            .meshcmds
-            .render_queue
-            .push(OpsRenderQueue::ops(entity, 0, render_queue as i32));
+        self.gltf_extras_uniforms(idmat, DISTORTION_UV, distortion_uv, DISTORTION_UV_UNIFORMS);
     }
 
     fn main_opacity(
         &mut self,
         root_path: &Path,
-        entity: Entity,
         idmat: Entity,
         info: &Value,
         textures: &Vec<pi_gltf::Texture>,
@@ -256,98 +306,14 @@ impl GLTFAPI<'_, '_> {
             self.opacity_texture(root_path, idmat, info, opacity_texture, textures)
         }
 
-        if let Some(_diffuse_color) = info.get("diffuseColor") {
-            self.commands.matcmds.vec4.push(OpsUniformVec4::ops(
-                idmat,
-                Atom::from(BlockMainTexture::KEY_COLOR),
-                1., //diffuse_color[0].as_f64().unwrap() as f32,
-                1., //diffuse_color[1].as_f64().unwrap() as f32,
-                1., //diffuse_color[2].as_f64().unwrap() as f32,
-                1., //diffuse_color[3].as_f64().unwrap() as f32,
-            ));
-        }
-
-        if let Some(emission_color) = info.get("emissionColor") {
-            self.commands.matcmds.vec4.push(OpsUniformVec4::ops(
-                idmat,
-                Atom::from(BlockEmissiveTexture::KEY_INFO),
-                emission_color[0].as_f64().unwrap() as f32,
-                emission_color[1].as_f64().unwrap() as f32,
-                emission_color[2].as_f64().unwrap() as f32,
-                emission_color[3].as_f64().unwrap() as f32,
-            ));
-        }
-
-        if let Some(alpha) = info.get("alpha") {
-            self.commands.matcmds.float.push(OpsUniformFloat::ops(
-                idmat,
-                Atom::from(BlockOpacity::KEY_ALPHA),
-                alpha.as_f64().unwrap() as f32,
-            ));
-        }
-
-        if let Some(alpha_cut_off) = info.get("alphaCutOff") {
-            self.commands.matcmds.float.push(OpsUniformFloat::ops(
-                idmat,
-                Atom::from(BlockCutoff::KEY_VALUE),
-                alpha_cut_off.as_f64().unwrap() as f32,
-            ));
-        }
-
-        let mut cull_mode = CullMode::Off;
-        if let Some(cull) = info.get("cull") {
-            let cull = cull.as_str().unwrap();
-            if cull == "off" {
-                cull_mode = CullMode::Off
-            } else if cull == "front" {
-                cull_mode = CullMode::Front
-            }
-        }
-
-        self.commands
-            .meshcmds
-            .cullmode
-            .push(OpsCullMode::ops(entity, cull_mode));
-
-        // TODO:????
-        let _depth_test = true;
+        self.diffuse_color(idmat, info);
 
-        let mut depth_write = false;
-        if let Some(z_write) = info.get("zWrite") {
-            if z_write.as_bool().unwrap() {
-                depth_write = true;
-            }
-        }
-        self.commands
-            .meshcmds
-            .depth_write
-            .push(OpsDepthWrite::ops(entity, depth_write));
-
-        let mut _alpha_mode = 0;
-        if let Some(_t_alpha_mode) = info.get("alphaMode") {
-            println!("=========== main_opacity alphaMode ===========");
-            let mut blend = ModelBlend::default();
-            blend.combine();
-            self.commands
-                .meshcmds
-                .blend
-                .push(OpsRenderBlend::ops(entity, blend));
-        }
-
-        let mut render_queue = 3000;
-        if let Some(t_render_queue) = info.get("renderQueue") {
-            render_queue = t_render_queue.as_i64().unwrap();
-        }
-        self.commands
-            .meshcmds
-            .render_queue
-            .push(OpsRenderQueue::ops(entity, 0, render_queue as i32));
+        self.gltf_extras_uniforms(idmat, MAIN_OPACITY, info, MAIN_OPACITY_UNIFORMS);
     }
 
     fn main_opacity_opacity_fresnel(
         &mut self,
         root_path: &Path,
-        entity: Entity,
         idmat: Entity,
         info: &Value,
         textures: &Vec<pi_gltf::Texture>,
@@ -377,123 +343,87 @@ impl GLTFAPI<'_, '_> {
             self.opacity_texture(root_path, idmat, info, opacity_texture, textures)
         }
 
-        if let Some(_diffuse_color) = info.get("diffuseColor") {
-            self.commands.matcmds.vec4.push(OpsUniformVec4::ops(
-                idmat,
-                Atom::from("diffuseColor"),
-                1.,//diffuse_color[0].as_f64().unwrap() as f32,
-                1.,//diffuse_color[1].as_f64().unwrap() as f32,
-                1.,//diffuse_color[2].as_f64().unwrap() as f32,
-                1.,//diffuse_color[3].as_f64().unwrap() as f32,
-            ));
-        }
+        self.diffuse_color(idmat, info);
 
-        if let Some(emission_color) = info.get("emissionColor") {
-            self.commands.matcmds.vec4.push(OpsUniformVec4::ops(
-                idmat,
-                Atom::from(BlockEmissiveTexture::KEY_INFO),
-                emission_color[0].as_f64().unwrap() as f32,
-                emission_color[1].as_f64().unwrap() as f32,
-                emission_color[2].as_f64().unwrap() as f32,
-                emission_color[3].as_f64().unwrap() as f32,
-            ));
-        }
-
-        if let Some(alpha) = info.get("alpha") {
-            self.commands.matcmds.float.push(OpsUniformFloat::ops(
-                idmat,
-                Atom::from(BlockOpacity::KEY_ALPHA),
-                alpha.as_f64().unwrap() as f32,
-            ));
-        }
+        self.gltf_extras_uniforms(
+            idmat,
+            MAIN_OPACITY_OPACITY_FRESNEL,
+            info,
+            MAIN_OPACITY_OPACITY_FRESNEL_UNIFORMS,
+        );
+    }
 
-        if let Some(alpha_cut_off) = info.get("alphaCutOff") {
-            self.commands.matcmds.float.push(OpsUniformFloat::ops(
-                idmat,
-                Atom::from(BlockCutoff::KEY_VALUE),
-                alpha_cut_off.as_f64().unwrap() as f32,
-            ));
-        }
+    fn two_opacity_mix(
+        &mut self,
+        root_path: &Path,
+        idmat: Entity,
+        info: &Value,
+        textures: &Vec<pi_gltf::Texture>,
+    ) {
+        ActionMaterial::regist_material_meta(
+            &self.commands.matcmds.metas,
+            &mut self.commands.matcmds.metas_wait,
+            KeyShaderMeta::from(TwoOpacityMixShader::KEY),
+            TwoOpacityMixShader::create(&self.commands.nodematblocks),
+        );
 
-        if let Some((ofbias, ofpower)) = info.get("OFBias").zip(info.get("OFPower")) {
-            self.commands.matcmds.vec2.push(OpsUniformVec2::ops(
-                idmat,
-                Atom::from(BlockOpacityFresnel::KEY_PARAM),
-                ofbias.as_f64().unwrap() as f32,
-                ofpower.as_f64().unwrap() as f32,
-            ));
-        }
+        self.commands.matcmds.create.push(OpsMaterialCreate::ops(
+            idmat,
+            TwoOpacityMixShader::KEY,
+            EPassTag::Transparent,
+        ));
 
-        if let Some(of_left) = info.get("OFLeft") {
-            self.commands.matcmds.vec4.push(OpsUniformVec4::ops(
-                idmat,
-                Atom::from(BlockOpacityFresnel::KEY_LEFT),
-                of_left[0].as_f64().unwrap() as f32,
-                of_left[1].as_f64().unwrap() as f32,
-                of_left[2].as_f64().unwrap() as f32,
-                of_left[3].as_f64().unwrap() as f32,
-            ));
+        if let Some(diffuse_texture) = info.get("diffuseTexture") {
+            self.diffuse_texture(root_path, idmat, info, diffuse_texture, textures)
         }
 
-        if let Some(of_right) = info.get("OFRight") {
-            self.commands.matcmds.vec4.push(OpsUniformVec4::ops(
-                idmat,
-                Atom::from(BlockOpacityFresnel::KEY_RIGHT),
-                of_right[0].as_f64().unwrap() as f32,
-                of_right[1].as_f64().unwrap() as f32,
-                of_right[2].as_f64().unwrap() as f32,
-                of_right[3].as_f64().unwrap() as f32,
-            ));
+        if let Some(opacity_texture) = info.get("opacityTexture") {
+            self.opacity_texture(root_path, idmat, info, opacity_texture, textures)
         }
 
-        let mut cull_mode = CullMode::Off;
-        if let Some(cull) = info.get("cull") {
-            let cull = cull.as_str().unwrap();
-            if cull == "off" {
-                cull_mode = CullMode::Off
-            } else if cull == "front" {
-                cull_mode = CullMode::Front
-            }
+        if let Some(opacity2_texture) = info.get("opacity2Texture") {
+            self.opacity2_texture(root_path, idmat, info, opacity2_texture, textures)
         }
 
-        self.commands
-            .meshcmds
-            .cullmode
-            .push(OpsCullMode::ops(entity, cull_mode));
+        self.diffuse_color(idmat, info);
 
-        // TODO:????
-        let _depth_test = true;
+        self.gltf_extras_uniforms(idmat, TWO_OPACITY_MIX, info, TWO_OPACITY_MIX_UNIFORMS);
+    }
 
-        let mut depth_write = false;
-        if let Some(z_write) = info.get("zWrite") {
-            if z_write.as_bool().unwrap() {
-                depth_write = true;
+    /// diffuseColor 为 rgb 或 rgba, 缺少的 alpha 为 1, 未配置时使用白色
+    fn diffuse_color(&mut self, idmat: Entity, info: &Value) {
+        let mut color = [1., 1., 1., 1.];
+        if let Some(diffuse_color) = info.get("diffuseColor").and_then(|v| v.as_array()) {
+            for (channel, value) in color.iter_mut().zip(diffuse_color) {
+                *channel = value.as_f64().unwrap_or(1.) as f32;
             }
         }
-        self.commands
-            .meshcmds
-            .depth_write
-            .push(OpsDepthWrite::ops(entity, depth_write));
-
-        let mut _alpha_mode = 0;
-        if let Some(_t_alpha_mode) = info.get("alphaMode") {
-            println!("=========== main_opacity_opacity_fresnel alphaMode ===========");
-            let mut blend = ModelBlend::default();
-            blend.combine();
-            self.commands
-                .meshcmds
-                .blend
-                .push(OpsRenderBlend::ops(entity, blend));
-        }
 
-        let mut render_queue = 3000;
-        if let Some(t_render_queue) = info.get("renderQueue") {
-            render_queue = t_render_queue.as_i64().unwrap();
+        self.commands.matcmds.vec4.push(OpsUniformVec4::ops(
+            idmat,
+            Atom::from(BlockMainTexture::KEY_COLOR),
+            color[0],
+            color[1],
+            color[2],
+            color[3],
+        ));
+    }
+
+    /// 纹理配置中的 KHR_texture_transform, 不支持的配置输出 "纹理名.原因"
+    fn extras_texture_transform(
+        &mut self,
+        idmat: Entity,
+        name: &str,
+        texture: &Value,
+        key_tilloff: &str,
+    ) {
+        let mut errors = vec![];
+        if let Some(transform) = GLTFTextureTransform::from_json(texture, &mut errors) {
+            if let Err(err) = self.gltf_texture_transform(idmat, key_tilloff, &transform) {
+                errors.push(err);
+            }
         }
-        self.commands
-            .meshcmds
-            .render_queue
-            .push(OpsRenderQueue::ops(entity, 0, render_queue as i32));
+        errors.iter().for_each(|err| log::warn!("{}.{}", name, err));
     }
 
     fn diffuse_texture(
@@ -505,61 +435,30 @@ impl GLTFAPI<'_, '_> {
         textures: &Vec<pi_gltf::Texture>,
     ) {
         let index = diffuse_texture["index"].as_u64().unwrap() as usize;
-        match textures[index].source().source() {
-            image::Source::View {
-                view: _,
-                mime_type: _,
-            } => todo!(),
-            image::Source::Uri { uri, mime_type: _ } => {
-                let path = root_path.parent().unwrap().join(uri);
-                println!("diffuse_texture: {:?}", path);
-                self.commands.matcmds.texture.push(OpsUniformTexture::ops(
-                    idmat,
-                    UniformTextureWithSamplerParam {
-                        slotname: Atom::from(BlockMainTexture::KEY_TEX),
-                        filter: true,
-                        sample: KeySampler::linear_repeat(),
-                        url: EKeyTexture::from(path.to_str().unwrap()),
-                    },
-                ));
-                // self.commands.matcmds.texture.push(OpsUniformTexture::ops(idmat, UniformTextureWithSamplerParam {
-                //     slotname: Atom::from(BlockOpacityTexture::KEY_TEX),
-                //     filter: true,
-                //     sample: KeySampler::linear_repeat(),
-                //     url: EKeyTexture::from("assets/images/eff_ui_ll_085.png"),
-                // }));
-            }
-        }
-        if let Some(diffuse_level) = info.get("diffuseLevel") {
-            self.commands.matcmds.float.push(OpsUniformFloat::ops(
-                idmat,
-                Atom::from(BlockMainTexture::KEY_TILLOFF),
-                diffuse_level.as_f64().unwrap() as f32,
-            ));
-        }
+        self.commands.matcmds.texture.push(OpsUniformTexture::ops(
+            idmat,
+            gltf_texture_param(
+                &self.commands.texture_urls,
+                root_path,
+                BlockMainTexture::KEY_TEX,
+                &textures[index],
+            ),
+        ));
 
-        if let Some((diffuse_ou, diffuse_ov)) = info.get("diffuseOU").zip(info.get("diffuseOV")) {
-            self.commands.matcmds.vec2.push(OpsUniformVec2::ops(
-                idmat,
-                Atom::from(BlockMainTexture::KEY_TILLOFF),
-                1000.0 / diffuse_ou.as_f64().unwrap() as f32,
-                1000.0 / diffuse_ov.as_f64().unwrap() as f32,
-            ));
-        }
+        self.gltf_extras_uniforms(idmat, "diffuseTexture", info, DIFFUSE_UNIFORMS);
+        self.gltf_extras_uniforms(
+            idmat,
+            "diffuseTexture",
+            diffuse_texture,
+            DIFFUSE_TEXTURE_UNIFORMS,
+        );
 
-        if let Some((scale, offset)) = diffuse_texture
-            .get("scale")
-            .zip(diffuse_texture.get("offset"))
-        {
-            self.commands.matcmds.vec4.push(OpsUniformVec4::ops(
-                idmat,
-                Atom::from(BlockMainTextureUVOffsetSpeed::KEY_PARAM),
-                scale[0].as_f64().unwrap() as f32,
-                scale[1].as_f64().unwrap() as f32,
-                offset[0].as_f64().unwrap() as f32,
-                offset[1].as_f64().unwrap() as f32,
-            ));
-        }
+        self.extras_texture_transform(
+            idmat,
+            "diffuseTexture",
+            diffuse_texture,
+            BlockMainTexture::KEY_TILLOFF,
+        );
     }
 
     fn emissive_texture(
@@ -571,57 +470,30 @@ impl GLTFAPI<'_, '_> {
         textures: &Vec<pi_gltf::Texture>,
     ) {
         let index = emissive_texture["index"].as_u64().unwrap() as usize;
-        match textures[index].source().source() {
-            image::Source::View {
-                view: _,
-                mime_type: _,
-            } => todo!(),
-            image::Source::Uri { uri, mime_type: _ } => {
-                let path = root_path.parent().unwrap().join(uri);
-                self.commands.matcmds.texture.push(OpsUniformTexture::ops(
-                    idmat,
-                    UniformTextureWithSamplerParam {
-                        slotname: Atom::from(BlockEmissiveTexture::KEY_TEX),
-                        filter: true,
-                        sample: KeySampler::default(),
-                        url: EKeyTexture::from(path.to_str().unwrap()),
-                    },
-                ));
-            }
-        }
-
-        if let Some(_emissive_map_level) = info.get("emissiveMapLevel") {
-
-            // self.commands.matcmds.float.push(OpsUniformFloat::ops(
-            //     idmat,
-            //     Atom::from("emissiveMapLevel"),
-            //     emissive_map_level.as_f64().unwrap() as f32,
-            // ));
-        }
+        self.commands.matcmds.texture.push(OpsUniformTexture::ops(
+            idmat,
+            gltf_texture_param(
+                &self.commands.texture_urls,
+                root_path,
+                BlockEmissiveTexture::KEY_TEX,
+                &textures[index],
+            ),
+        ));
 
-        if let Some((emission_ou, emission_ov)) = info.get("emissionOU").zip(info.get("emissionOV"))
-        {
-            self.commands.matcmds.vec2.push(OpsUniformVec2::ops(
-                idmat,
-                Atom::from(BlockEmissiveTextureUVOffsetSpeed::KEY_PARAM),
-                emission_ou.as_f64().unwrap() as f32,
-                emission_ov.as_f64().unwrap() as f32,
-            ));
-        }
+        self.gltf_extras_uniforms(idmat, "emissionTexture", info, EMISSIVE_UNIFORMS);
+        self.gltf_extras_uniforms(
+            idmat,
+            "emissionTexture",
+            emissive_texture,
+            EMISSIVE_TEXTURE_UNIFORMS,
+        );
 
-        if let Some((scale, offset)) = emissive_texture
-            .get("scale")
-            .zip(emissive_texture.get("offset"))
-        {
-            self.commands.matcmds.vec4.push(OpsUniformVec4::ops(
-                idmat,
-                Atom::from(BlockEmissiveTexture::KEY_TILLOFF),
-                scale[0].as_f64().unwrap() as f32,
-                scale[1].as_f64().unwrap() as f32,
-                offset[0].as_f64().unwrap() as f32,
-                offset[1].as_f64().unwrap() as f32,
-            ));
-        }
+        self.extras_texture_transform(
+            idmat,
+            "emissionTexture",
+            emissive_texture,
+            BlockEmissiveTexture::KEY_TILLOFF,
+        );
     }
 
     fn opacity_texture(
@@ -633,55 +505,65 @@ impl GLTFAPI<'_, '_> {
         textures: &Vec<pi_gltf::Texture>,
     ) {
         let index = opacity_texture["index"].as_u64().unwrap() as usize;
-        match textures[index].source().source() {
-            image::Source::View {
-                view: _,
-                mime_type: _,
-            } => todo!(),
-            image::Source::Uri { uri, mime_type: _ } => {
-                let path = root_path.parent().unwrap().join(uri);
-                self.commands.matcmds.texture.push(OpsUniformTexture::ops(
-                    idmat,
-                    UniformTextureWithSamplerParam {
-                        slotname: Atom::from(BlockOpacityTexture::KEY_TEX),
-                        filter: true,
-                        sample: KeySampler::default(),
-                        url: EKeyTexture::from(path.to_str().unwrap()),
-                    },
-                ));
-            }
-        }
+        self.commands.matcmds.texture.push(OpsUniformTexture::ops(
+            idmat,
+            gltf_texture_param(
+                &self.commands.texture_urls,
+                root_path,
+                BlockOpacityTexture::KEY_TEX,
+                &textures[index],
+            ),
+        ));
 
-        if let Some(opacity_level) = info.get("opacityLevel") {
-            self.commands.matcmds.float.push(OpsUniformFloat::ops(
-                idmat,
-                Atom::from(BlockOpacityTexture::KEY_LEVEL),
-                opacity_level.as_f64().unwrap() as f32,
-            ));
-        }
+        self.gltf_extras_uniforms(idmat, "opacityTexture", info, OPACITY_UNIFORMS);
+        self.gltf_extras_uniforms(
+            idmat,
+            "opacityTexture",
+            opacity_texture,
+            OPACITY_TEXTURE_UNIFORMS,
+        );
 
-        if let Some((opacity_ou, opacity_ov)) = info.get("opacityOU").zip(info.get("opacityOV")) {
-            self.commands.matcmds.vec2.push(OpsUniformVec2::ops(
-                idmat,
-                Atom::from(BlockOpacityTexture::KEY_TILLOFF),
-                opacity_ou.as_f64().unwrap() as f32,
-                opacity_ov.as_f64().unwrap() as f32,
-            ));
-        }
+        self.extras_texture_transform(
+            idmat,
+            "opacityTexture",
+            opacity_texture,
+            BlockOpacityTexture::KEY_TILLOFF,
+        );
+    }
 
-        if let Some((scale, offset)) = opacity_texture
-            .get("scale")
-            .zip(opacity_texture.get("offset"))
-        {
-            self.commands.matcmds.vec4.push(OpsUniformVec4::ops(
-                idmat,
-                Atom::from(BlockOpacityTextureUVOffsetSpeed::KEY_PARAM),
-                scale[0].as_f64().unwrap() as f32,
-                scale[1].as_f64().unwrap() as f32,
-                offset[0].as_f64().unwrap() as f32,
-                offset[1].as_f64().unwrap() as f32,
-            ));
-        }
+    fn opacity2_texture(
+        &mut self,
+        root_path: &Path,
+        idmat: Entity,
+        info: &Value,
+        opacity2_texture: &Value,
+        textures: &Vec<pi_gltf::Texture>,
+    ) {
+        let index = opacity2_texture["index"].as_u64().unwrap() as usize;
+        self.commands.matcmds.texture.push(OpsUniformTexture::ops(
+            idmat,
+            gltf_texture_param(
+                &self.commands.texture_urls,
+                root_path,
+                BlockOpacity2Texture::KEY_TEX,
+                &textures[index],
+            ),
+        ));
+
+        self.gltf_extras_uniforms(idmat, "opacity2Texture", info, OPACITY2_UNIFORMS);
+        self.gltf_extras_uniforms(
+            idmat,
+            "opacity2Texture",
+            opacity2_texture,
+            OPACITY2_TEXTURE_UNIFORMS,
+        );
+
+        self.extras_texture_transform(
+            idmat,
+            "opacity2Texture",
+            opacity2_texture,
+            BlockOpacity2Texture::KEY_TILLOFF,
+        );
     }
 
     fn mask_texture(
@@ -692,35 +574,23 @@ impl GLTFAPI<'_, '_> {
         textures: &Vec<pi_gltf::Texture>,
     ) {
         let index = mask_texture["index"].as_u64().unwrap() as usize;
-        match textures[index].source().source() {
-            image::Source::View {
-                view: _,
-                mime_type: _,
-            } => todo!(),
-            image::Source::Uri { uri, mime_type: _ } => {
-                let path = root_path.parent().unwrap().join(uri);
-                println!("mask_texture path: {:?}", path);
-                self.commands.matcmds.texture.push(OpsUniformTexture::ops(
-                    idmat,
-                    UniformTextureWithSamplerParam {
-                        slotname: Atom::from(BlockMaskTexture::KEY_TEX),
-                        filter: true,
-                        sample: KeySampler::default(),
-                        url: EKeyTexture::from(path.to_str().unwrap()),
-                    },
-                ));
-            }
-        }
+        self.commands.matcmds.texture.push(OpsUniformTexture::ops(
+            idmat,
+            gltf_texture_param(
+                &self.commands.texture_urls,
+                root_path,
+                BlockMaskTexture::KEY_TEX,
+                &textures[index],
+            ),
+        ));
 
-        if let Some((scale, offset)) = mask_texture.get("scale").zip(mask_texture.get("offset")) {
-            self.commands.matcmds.vec4.push(OpsUniformVec4::ops(
-                idmat,
-                Atom::from(BlockMaskTextureUVOffsetSpeed::KEY_PARAM),
-                scale[0].as_f64().unwrap() as f32,
-                scale[1].as_f64().unwrap() as f32,
-                offset[0].as_f64().unwrap() as f32,
-                offset[1].as_f64().unwrap() as f32,
-            ));
-        }
+        self.gltf_extras_uniforms(idmat, "maskTexture", mask_texture, MASK_TEXTURE_UNIFORMS);
+
+        self.extras_texture_transform(
+            idmat,
+            "maskTexture",
+            mask_texture,
+            BlockMaskTexture::KEY_TILLOFF,
+        );
     }
 }
@@ -1,2 +1,7 @@
 pub mod particle;
-pub mod material;
\ No newline at end of file
+pub mod particle_config;
+pub mod particle_control;
+pub mod material;
+pub mod uniform;
+pub mod render_state;
+pub mod shader;
@@ -1,638 +1,802 @@
-use crate::interface::GLTFAPI;
+use crate::interface::{EGLTFMaterial, GLTFMaterialState, GLTFMeshMaterial, GLTFAPI};
+use super::{
+    particle_config::{
+        from_json, gltf_format_particle_cfg, gltf_particle_play_on_awake, TrailJson,
+    },
+    particle_control::ParticlePlayback,
+};
 use particle::{
-    emitter::ishape_emitter_type::{EBoxShapeMode, EShapeEmitterArcMode},
+    emitter::ishape_emitter_type::EBoxShapeMode,
     extend::format_mesh_particle,
     iparticle_system_config::{
-        FourGradientInfo, IParticleSystemConfig, IShape, IShapeArc, IShapeArcBurstSpread,
-        IShapeArcLoop, IShapeArcPingPong, IShapeArcRandom, IShapeBox, IShapeCircle, IShapeCone,
-        IShapeEdge, IShapeHemisphere, IShapeRectangle, IShapeSphere, ITextureSheet, OneParamInfo,
-        ParamInfo, ThreeParamInfo,
+        FourGradientInfo, IParticleSystemConfig, IShape, IShapeArc, ITextureSheet, ITrail,
+        OneParamInfo, ParamInfo, ThreeParamInfo,
     },
     mesh_particle_system::MeshParticleSystem,
-    modifier::texture_sheet::{AnimationMode, RowMode, TimeMode},
+    modifier::{
+        texture_sheet::{AnimationMode, RowMode, TimeMode},
+        trail::{ETrailMode, ETrailTextureMode},
+    },
     particle_system_tool::{
         EMeshParticleScaleMode, EMeshParticleSpaceMode, ERenderAlignment, ERenderMode,
     },
 };
 use pi_engine_shell::prelude::*;
 use pi_gltf::json::Value;
+use pi_mesh_builder::quad::QuadBuilder;
+use pi_scene_context::prelude::*;
 
 #[derive(Component)]
 pub struct Particle(pub MeshParticleSystem);
 
+/// 粒子网格, 每个 primitive 一个, 共用粒子实例数据; 节点没有 mesh 时为默认四边形
 #[derive(Component)]
-pub struct MeshParticleMeshID(pub Entity);
-
-impl GLTFAPI<'_, '_> {
-    pub fn gltf_extras_particle(&mut self, extras: &Value) -> MeshParticleSystem {
-        let config = gltf_format_particle_cfg(extras);
+pub struct MeshParticleMeshID(pub Vec<Entity>);
 
-        let mut mp = MeshParticleSystem::new();
-        format_mesh_particle(&config, &mut mp);
-        mp.build();
-        mp.start();
-
-        mp
-    }
+/// 拖尾网格, 顶点数据由粒子系统运行时按拖尾长度生成
+#[derive(Component)]
+pub struct MeshParticleTrailID(pub Entity);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EParticleSortMode {
+    None,
+    /// 由远及近
+    Distance,
+    OldestInFront,
+    YoungestInFront,
 }
 
-fn gltf_format_particle_cfg(mesh_particle_cfg: &Value) -> IParticleSystemConfig {
-    let mut config = IParticleSystemConfig::default();
-
-    if let Some(name) = mesh_particle_cfg.get("name") {
-        config.name = name.as_str().unwrap().to_string()
-    }
-
-    if let Some(duration) = mesh_particle_cfg.get("duration") {
-        config.duration = duration.as_f64().unwrap() as f32;
-    }
-
-    if let Some(start_delay) = mesh_particle_cfg.get("startDelay") {
-        config.start_delay = start_delay.as_f64().unwrap() as f32;
-    }
-
-    if let Some(looping) = mesh_particle_cfg.get("looping") {
-        config.looping = looping.as_u64().unwrap() as u32;
-    }
-
-    if let Some(prewarm) = mesh_particle_cfg.get("prewarm") {
-        let prewarm = prewarm.as_i64().unwrap();
-        if prewarm == 0 {
-            config.prewarm = false;
-        } else {
-            config.prewarm = true;
-        }
-    }
-
-    if let Some(simulation_space_is_world) = mesh_particle_cfg.get("simulationSpaceIsWorld") {
-        let simulation_space_is_world = simulation_space_is_world.as_u64().unwrap();
-        if simulation_space_is_world == 0 {
-            config.simulation_space_is_world = EMeshParticleSpaceMode::Local;
-        } else if simulation_space_is_world == 1 {
-            config.simulation_space_is_world = EMeshParticleSpaceMode::World;
+impl EParticleSortMode {
+    pub fn from_json(value: u32) -> Result<Self, String> {
+        match value {
+            0 => Ok(Self::None),
+            1 => Ok(Self::Distance),
+            2 => Ok(Self::OldestInFront),
+            3 => Ok(Self::YoungestInFront),
+            _ => Err(format!("{} is not exits", value)),
         }
     }
+}
 
-    if let Some(scaling_mode) = mesh_particle_cfg.get("scalingMode") {
-        let scaling_mode = scaling_mode.as_u64().unwrap();
-        if scaling_mode == 0 {
-            config.scaling_mode = EMeshParticleScaleMode::Hierarchy;
-        } else if scaling_mode == 1 {
-            config.scaling_mode = EMeshParticleScaleMode::Local;
-        } else if scaling_mode == 2 {
-            config.scaling_mode = EMeshParticleScaleMode::Shape;
-        }
-    }
+/// 粒子实例数据, 世界矩阵总是存在
+/// 只声明粒子系统写入的实例数据, 避免 shader 拿到空的实例 buffer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EParticleVertexStream {
+    Color,
+    TillOff,
+}
 
-    if let Some(render_alignment) = mesh_particle_cfg.get("renderAlignment") {
-        let render_alignment = render_alignment.as_u64().unwrap();
-        if render_alignment == 0 {
-            config.render_alignment = ERenderAlignment::View;
-        } else if render_alignment == 1 {
-            config.render_alignment = ERenderAlignment::World;
-        } else if render_alignment == 2 {
-            config.render_alignment = ERenderAlignment::Velocity;
-        } else if render_alignment == 3 {
-            config.render_alignment = ERenderAlignment::Facing;
-        } else if render_alignment == 4 {
-            config.render_alignment = ERenderAlignment::Local;
+impl EParticleVertexStream {
+    pub fn from_json(value: &str) -> Result<Self, String> {
+        match value {
+            "color" => Ok(Self::Color),
+            "tilloff" => Ok(Self::TillOff),
+            "custom1" | "custom2" | "velocity" | "age" => {
+                Err(format!("{} is not supported, ignored", value))
+            }
+            _ => Err(format!("{} is not exits", value)),
         }
     }
 
-    if let Some(render_mode) = mesh_particle_cfg.get("renderMode") {
-        let render_mode = render_mode.as_u64().unwrap();
-        if render_mode == 0 {
-            config.render_mode = ERenderMode::Billboard;
-        } else if render_mode == 1 {
-            config.render_mode = ERenderMode::StretchedBillboard;
-        } else if render_mode == 2 {
-            config.render_mode = ERenderMode::HorizontalBillboard;
-        } else if render_mode == 3 {
-            config.render_mode = ERenderMode::VerticalBillboard;
-        } else if render_mode == 4 {
-            config.render_mode = ERenderMode::Mesh;
-        } else {
-            config.render_mode = ERenderMode::None;
+    pub fn buffer_desc(&self) -> VertexBufferDesc {
+        match self {
+            Self::Color => VertexBufferDesc::instance_color(),
+            Self::TillOff => VertexBufferDesc::instance_tilloff(),
         }
     }
+}
 
-    if let Some(stretched_length_scale) = mesh_particle_cfg.get("stretchedLengthScale") {
-        config.stretched_length_scale = stretched_length_scale.as_f64().unwrap() as f32;
-    }
-
-    if let Some(stretched_velocity_scale) = mesh_particle_cfg.get("stretchedVelocityScale") {
-        config.stretched_velocity_scale = stretched_velocity_scale.as_f64().unwrap() as f32;
-    }
-
-    if let Some(stretched_velocity_scale) = mesh_particle_cfg.get("renderPivot") {
-        config.render_pivot = Some([
-            stretched_velocity_scale[0].as_f64().unwrap() as f32,
-            stretched_velocity_scale[1].as_f64().unwrap() as f32,
-            stretched_velocity_scale[2].as_f64().unwrap() as f32,
-        ]);
-    }
-
-    if let Some(max_particles) = mesh_particle_cfg.get("maxParticles") {
-        config.max_particles = max_particles.as_f64().unwrap() as f32;
-    }
-
-    if let Some(start_speed) = mesh_particle_cfg.get("startSpeed") {
-        config.start_speed = format_one_param_info(start_speed);
-    }
-
-    if let Some(lifetime) = mesh_particle_cfg.get("lifetime") {
-        config.lifetime = format_one_param_info(lifetime);
-    }
-
-    if let Some(start_color) = mesh_particle_cfg.get("startColor") {
-        config.start_color = format_four_gradient_info(start_color);
-    }
-
-    if let Some(start_size) = mesh_particle_cfg.get("startSize") {
-        config.start_size = format_param_info(start_size);
-    }
-
-    if let Some(start_rotation) = mesh_particle_cfg.get("startRotation") {
-        config.start_rotation = format_param_info(start_rotation);
-    }
-
-    if let Some(gravity) = mesh_particle_cfg.get("gravity") {
-        config.gravity = format_one_param_info(gravity)
-    }
+/// 粒子渲染设置, 插入在粒子节点上, 创建粒子网格时按 streams 生成实例数据格式
+///   * sort / min_size / max_size: 由粒子渲染系统读取, 尺寸为粒子屏幕尺寸占视口的比例
+///   * streams: 未配置时为 color + tilloff
+#[derive(Debug, Clone, Component)]
+pub struct ParticleRenderSettings {
+    pub sort: EParticleSortMode,
+    pub min_size: f32,
+    pub max_size: f32,
+    pub streams: Vec<EParticleVertexStream>,
+}
 
-    if let Some(emission) = mesh_particle_cfg.get("emission") {
-        let a = emission[0].as_f64().unwrap() as f32;
-        let mut v2 = None;
-        if let Some(e2) = emission[1].as_array() {
-            let mut temp = vec![];
-            for e in e2 {
-                println!("{:?}", e);
-                temp.push([
-                    e[0].as_f64().unwrap() as f32,
-                    e[1].as_f64().unwrap() as f32,
-                    e[2].as_f64().unwrap() as f32,
-                    e[3].as_f64().unwrap() as f32,
-                ])
-            }
-            v2 = Some(temp);
+impl Default for ParticleRenderSettings {
+    fn default() -> Self {
+        Self {
+            sort: EParticleSortMode::None,
+            min_size: 0.,
+            max_size: 0.5,
+            streams: vec![EParticleVertexStream::Color, EParticleVertexStream::TillOff],
         }
-        config.emission = (a, v2);
-    }
-
-    if let Some(shape) = mesh_particle_cfg.get("shape") {
-        config.shape = format_shape(shape);
-    }
-
-    if let Some(velocity_over_lifetime) = mesh_particle_cfg.get("velocityOverLifetime") {
-        config.velocity_over_lifetime = Some(format_param_info(velocity_over_lifetime));
-    }
-
-    if let Some(velocity_over_lifetime_is_local) =
-        mesh_particle_cfg.get("velocityOverLifetimeIsLocal")
-    {
-        config.velocity_over_lifetime_is_local =
-            Some(velocity_over_lifetime_is_local.as_i64().unwrap() as u32);
-    }
-
-    if let Some(cfg) = mesh_particle_cfg.get("limitVelocityOverLifetime") {
-        config.limit_velocity_over_lifetime = Some(format_one_param_info(cfg));
     }
+}
 
-    if let Some(cfg) = mesh_particle_cfg.get("limitVelocityOverLifetime") {
-        config.limit_velocity_over_lifetime = Some(format_one_param_info(cfg));
+impl ParticleRenderSettings {
+    pub fn vertex_buffer_desc(&self) -> Vec<VertexBufferDesc> {
+        let mut result = vec![VertexBufferDesc::instance_world_matrix()];
+        result.extend(self.streams.iter().map(EParticleVertexStream::buffer_desc));
+        result
     }
+}
 
-    if let Some(cfg) = mesh_particle_cfg.get("limitVelocityOverLifetimeDampen") {
-        config.limit_velocity_over_lifetime_dampen = Some(cfg.as_f64().unwrap() as f32);
-    }
+impl GLTFAPI<'_, '_> {
+    /// 错误的字段输出错误并使用默认值, meshParticle 不是对象时返回 None
+    /// 粒子系统由 ParticlePlayback 启动
+    pub fn gltf_extras_particle(
+        &mut self,
+        extras: &Value,
+    ) -> Option<(MeshParticleSystem, ParticlePlayback)> {
+        let mut errors = vec![];
+        let config = gltf_format_particle_cfg(extras, &mut errors);
+        errors.iter().for_each(|err| log::warn!("{}", err));
+        let mut config = match config {
+            Ok(config) => config,
+            Err(err) => {
+                log::warn!("{}", err);
+                return None;
+            }
+        };
 
-    if let Some(cfg) = mesh_particle_cfg.get("forceOverLifetime") {
-        config.force_over_lifetime = Some(format_param_info(cfg));
-    }
+        let playback = ParticlePlayback::new(&config, gltf_particle_play_on_awake(extras));
+        config.start_delay = 0.;
 
-    if let Some(cfg) = mesh_particle_cfg.get("forceSpaceIsLocal") {
-        config.force_space_is_local = Some(cfg.as_i64().unwrap() as u32);
-    }
+        let mut mp = MeshParticleSystem::new();
+        format_mesh_particle(&config, &mut mp);
+        mp.build();
 
-    if let Some(cfg) = mesh_particle_cfg.get("colorOverLifetime") {
-        config.color_over_lifetime = Some(format_four_gradient_info(cfg));
+        Some((mp, playback))
+    }
+
+    /// 拖尾 / 默认四边形未指定材质时使用双面透明的 unlit 材质
+    pub fn gltf_particle_default_material(&mut self, idmat: Entity) -> GLTFMeshMaterial {
+        let state = GLTFMaterialState {
+            pass: EPassTag::Transparent,
+            blend: true,
+            cutoff: None,
+            cull: CullMode::Off,
+        };
+        self.gltf_create_unlit_material(idmat, state.pass);
+        GLTFMeshMaterial {
+            state,
+            created: EGLTFMaterial::Unlit(idmat),
+            extras: None,
+        }
     }
 
-    if let Some(cfg) = mesh_particle_cfg.get("colorBySpeed") {
-        config.color_by_speed = Some((
-            format_four_gradient_info(&cfg[0]),
-            cfg[1].as_f64().unwrap() as f32,
-            cfg[2].as_f64().unwrap() as f32,
+    /// 拖尾网格, parent: 世界空间的拖尾挂在场景下, 否则挂在粒子节点下
+    /// 顶点 buffer 创建时为空, 由粒子系统运行时写入
+    pub fn gltf_particle_trail(
+        &mut self,
+        trail: Entity,
+        id_geo: Entity,
+        parent: Entity,
+        material: &GLTFMeshMaterial,
+    ) {
+        self.commands.meshcmds.create.push(OpsMeshCreation::ops(
+            self.scene_id,
+            trail,
+            String::from("trail"),
         ));
-    }
-
-    if let Some(cfg) = mesh_particle_cfg.get("sizeOverLifetime") {
-        config.size_over_lifetime = Some(format_param_info(cfg));
-    }
-
-    if let Some(cfg) = mesh_particle_cfg.get("sizeBySpeed") {
-        config.size_by_speed = Some((
-            format_one_param_info(&cfg[0]),
-            cfg[1].as_f64().unwrap() as f32,
-            cfg[2].as_f64().unwrap() as f32,
+        self.commands
+            .transformcmds
+            .tree
+            .push(OpsTransformNodeParent::ops(trail, parent));
+
+        let id = format!("{:?}; trail", trail);
+        self.gltf_create_buffer(id.as_str(), vec![]);
+        self.gltf_geometry(trail, id_geo, vec![trail_vertex_buffer_desc(id)], None);
+        self.gltf_apply_material(trail, material);
+    }
+
+    /// 粒子节点没有 mesh 时使用的公告板四边形, 需要注册 PluginQuadBuilder
+    pub fn gltf_particle_quad(
+        &mut self,
+        mesh: Entity,
+        id_geo: Entity,
+        render: &ParticleRenderSettings,
+        material: &GLTFMeshMaterial,
+    ) {
+        self.commands.meshcmds.create.push(OpsMeshCreation::ops(
+            self.scene_id,
+            mesh,
+            String::from("particle"),
         ));
-    }
-
-    if let Some(cfg) = mesh_particle_cfg.get("rotationOverLifetime") {
-        config.rotation_over_lifetime = Some(format_param_info(&cfg));
-    }
+        self.commands
+            .transformcmds
+            .tree
+            .push(OpsTransformNodeParent::ops(mesh, self.scene_id));
 
-    if let Some(cfg) = mesh_particle_cfg.get("rotationBySpeed") {
-        config.rotation_by_speed = Some((
-            format_one_param_info(&cfg[0]),
-            cfg[1].as_f64().unwrap() as f32,
-            cfg[2].as_f64().unwrap() as f32,
-        ));
-    }
-
-    if let Some(cfg) = mesh_particle_cfg.get("textureSheet") {
-        config.texture_sheet = Some(format_texture_sheet(cfg));
-    }
-
-    if let Some(cfg) = mesh_particle_cfg.get("texture") {
-        config.texture = Some(cfg.as_str().unwrap().to_string())
-    }
-
-    if let Some(_cfg) = mesh_particle_cfg.get("trail") {
-        todo!()
-    }
-
-    if let Some(cfg) = mesh_particle_cfg.get("orbtialVelocity") {
-        config.orbtial_velocity = Some(format_param_info(cfg));
-    }
-
-    if let Some(cfg) = mesh_particle_cfg.get("orbitalOffset") {
-        config.orbital_offset = Some(format_param_info(cfg));
-    }
-
-    if let Some(cfg) = mesh_particle_cfg.get("orbitalRadial") {
-        config.orbital_radial = Some(format_one_param_info(cfg));
+        let mut descs = QuadBuilder::attrs_meta();
+        descs.extend(render.vertex_buffer_desc());
+        self.gltf_geometry(mesh, id_geo, descs, Some(QuadBuilder::indices_meta()));
+        self.gltf_apply_material(mesh, material);
     }
+}
 
-    if let Some(cfg) = mesh_particle_cfg.get("speedModifier") {
-        config.speed_modifier = Some(format_one_param_info(cfg));
-    }
+fn number_json(value: f32) -> Value {
+    Value::from(value as f64)
+}
 
-    if let Some(cfg) = mesh_particle_cfg.get("renderPivot") {
-        config.render_pivot = Some([
-            cfg[0].as_f64().unwrap() as f32,
-            cfg[1].as_f64().unwrap() as f32,
-            cfg[2].as_f64().unwrap() as f32,
-        ]);
-    }
+fn numbers_json(values: &[f32]) -> Value {
+    Value::Array(values.iter().map(|v| number_json(*v)).collect())
+}
 
-    if let Some(cfg) = mesh_particle_cfg.get("custom1") {
-        config.custom1 = Some([
-            format_one_param_info(&cfg[0]),
-            format_one_param_info(&cfg[1]),
-            format_one_param_info(&cfg[2]),
-            format_one_param_info(&cfg[3]),
-        ]);
-    }
+fn curve_json(curve: &(Vec<Vec<f32>>, f32)) -> Value {
+    Value::Array(vec![
+        Value::Array(curve.0.iter().map(|v| numbers_json(v)).collect()),
+        number_json(curve.1),
+    ])
+}
 
-    config
+fn gradient_json(gradient: &[Vec<[f32; 2]>; 4]) -> Value {
+    Value::Array(
+        gradient
+            .iter()
+            .map(|channel| Value::Array(channel.iter().map(|v| numbers_json(v)).collect()))
+            .collect(),
+    )
 }
 
-fn format_one_param_info(config: &Value) -> OneParamInfo {
-    match config[1].as_i64().unwrap() {
-        1 => OneParamInfo::TInterpolateConstant(config[2].as_f64().unwrap() as f32),
-        2 => OneParamInfo::TInterpolateTwoConstants(
-            config[2].as_f64().unwrap() as f32,
-            config[3].as_f64().unwrap() as f32,
-        ),
-        4 => {
-            let mut res = vec![];
-            for v in config[2][0].as_array().unwrap() {
-                let mut t = vec![];
-                for v_t in v.as_array().unwrap() {
-                    t.push(v_t.as_f64().unwrap() as f32);
-                }
-                res.push(t);
-            }
-            let s = config[2][1].as_f64().unwrap() as f32;
-            OneParamInfo::TInterpolateCurve((res, s))
+/// 参数转换为配置中的 json 格式, 与 particle_config 中的 Tagged 解析互逆
+pub fn one_param_info_json(info: &OneParamInfo) -> Value {
+    let mut result = vec![Value::from(1)];
+    match info {
+        OneParamInfo::TInterpolateConstant(v) => {
+            result.push(Value::from(1));
+            result.push(number_json(*v));
+        }
+        OneParamInfo::TInterpolateTwoConstants(v1, v2) => {
+            result.push(Value::from(2));
+            result.push(number_json(*v1));
+            result.push(number_json(*v2));
         }
-        8 => todo!(),
-        _ => {
-            panic!("config of OneParamInfo: {} is exits!!!!", config)
+        OneParamInfo::TInterpolateCurve(curve) => {
+            result.push(Value::from(4));
+            result.push(curve_json(curve));
+        }
+        OneParamInfo::TInterpolateTwoCurves(curve1, curve2) => {
+            result.push(Value::from(8));
+            result.push(curve_json(curve1));
+            result.push(curve_json(curve2));
         }
     }
+    Value::Array(result)
 }
 
-fn format_three_param_info(config: &Value) -> ThreeParamInfo {
-    match config[1].as_i64().unwrap() {
-        1 => ThreeParamInfo::TInterpolateConstant([
-            config[2][0].as_f64().unwrap() as f32,
-            config[2][1].as_f64().unwrap() as f32,
-            config[2][2].as_f64().unwrap() as f32,
-        ]),
-        2 => ThreeParamInfo::TInterpolateTwoConstants(
-            [
-                config[2][0].as_f64().unwrap() as f32,
-                config[2][1].as_f64().unwrap() as f32,
-                config[2][2].as_f64().unwrap() as f32,
-            ],
-            [
-                config[3][0].as_f64().unwrap() as f32,
-                config[3][1].as_f64().unwrap() as f32,
-                config[3][2].as_f64().unwrap() as f32,
-            ],
-        ),
-        4 | 8 => todo!(),
-        _ => {
-            panic!("config of ThreeParamInfo: {} is exits!!!!", config)
+pub fn three_param_info_json(info: &ThreeParamInfo) -> Value {
+    let curves_json = |curves: &[(Vec<Vec<f32>>, f32); 3]| {
+        Value::Array(curves.iter().map(curve_json).collect())
+    };
+
+    let mut result = vec![Value::from(3)];
+    match info {
+        ThreeParamInfo::TInterpolateConstant(v) => {
+            result.push(Value::from(1));
+            result.push(numbers_json(v));
+        }
+        ThreeParamInfo::TInterpolateTwoConstants(v1, v2) => {
+            result.push(Value::from(2));
+            result.push(numbers_json(v1));
+            result.push(numbers_json(v2));
+        }
+        ThreeParamInfo::TInterpolateCurve(curves) => {
+            result.push(Value::from(4));
+            result.push(curves_json(curves));
+        }
+        ThreeParamInfo::TInterpolateTwoCurves(curves1, curves2) => {
+            result.push(Value::from(8));
+            result.push(curves_json(curves1));
+            result.push(curves_json(curves2));
         }
     }
+    Value::Array(result)
 }
 
-fn format_param_info(config: &Value) -> ParamInfo {
-    match config[0].as_i64().unwrap() {
-        1 => ParamInfo::OneParamInfo(format_one_param_info(config)),
-        3 => ParamInfo::ThreeParamInfo(format_three_param_info(config)),
-        _ => {
-            panic!("config of ParamInfo: {} is exits!!!!", config)
-        }
+pub fn param_info_json(info: &ParamInfo) -> Value {
+    match info {
+        ParamInfo::OneParamInfo(info) => one_param_info_json(info),
+        ParamInfo::ThreeParamInfo(info) => three_param_info_json(info),
     }
 }
 
-fn format_four_gradient_info(config: &Value) -> FourGradientInfo {
-    match config[1].as_i64().unwrap() {
-        1 => FourGradientInfo::TInterpolateColor([
-            config[2][0].as_f64().unwrap() as f32,
-            config[2][1].as_f64().unwrap() as f32,
-            config[2][2].as_f64().unwrap() as f32,
-            config[2][3].as_f64().unwrap() as f32,
-        ]),
-        2 => FourGradientInfo::TInterpolateTwoColors(
-            [
-                config[2][0].as_f64().unwrap() as f32,
-                config[2][1].as_f64().unwrap() as f32,
-                config[2][2].as_f64().unwrap() as f32,
-                config[2][3].as_f64().unwrap() as f32,
-            ],
-            [
-                config[3][0].as_f64().unwrap() as f32,
-                config[3][1].as_f64().unwrap() as f32,
-                config[3][2].as_f64().unwrap() as f32,
-                config[3][3].as_f64().unwrap() as f32,
-            ],
-        ),
-        4 => {
-            let mut vec1 = vec![];
-            for v in config[2][0].as_array().unwrap() {
-                vec1.push([v[0].as_f64().unwrap() as f32, v[1].as_f64().unwrap() as f32]);
-            }
-            let mut vec2 = vec![];
-            for v in config[2][1].as_array().unwrap() {
-                vec2.push([v[0].as_f64().unwrap() as f32, v[1].as_f64().unwrap() as f32]);
-            }
-            let mut vec3 = vec![];
-            for v in config[2][2].as_array().unwrap() {
-                vec3.push([v[0].as_f64().unwrap() as f32, v[1].as_f64().unwrap() as f32]);
-            }
-            let mut vec4 = vec![];
-            for v in config[2][3].as_array().unwrap() {
-                vec4.push([v[0].as_f64().unwrap() as f32, v[1].as_f64().unwrap() as f32]);
-            }
-            FourGradientInfo::TInterpolateGradient([vec1, vec2, vec3, vec4])
+pub fn four_gradient_info_json(info: &FourGradientInfo) -> Value {
+    let mut result = vec![Value::from(4)];
+    match info {
+        FourGradientInfo::TInterpolateColor(v) => {
+            result.push(Value::from(1));
+            result.push(numbers_json(v));
+        }
+        FourGradientInfo::TInterpolateTwoColors(v1, v2) => {
+            result.push(Value::from(2));
+            result.push(numbers_json(v1));
+            result.push(numbers_json(v2));
         }
-        8 => todo!(),
-        16 => FourGradientInfo::TInterpolateRandom,
-        _ => {
-            panic!("config of FourGradientInfo: {} is exits!!!!", config)
+        FourGradientInfo::TInterpolateGradient(gradient) => {
+            result.push(Value::from(4));
+            result.push(gradient_json(gradient));
+        }
+        FourGradientInfo::TInterpolateTwoGradients(gradient1, gradient2) => {
+            result.push(Value::from(8));
+            result.push(gradient_json(gradient1));
+            result.push(gradient_json(gradient2));
+        }
+        FourGradientInfo::TInterpolateRandom => {
+            result.push(Value::from(16));
         }
     }
+    Value::Array(result)
 }
 
-fn format_shape(config: &Value) -> IShape {
-    let mut radius = 0.0;
-    if let Some(v) = config.get("radius") {
-        radius = v.as_f64().unwrap() as f32;
-    };
-
-    let mut height = 0.0;
-    if let Some(v) = config.get("height") {
-        height = v.as_f64().unwrap() as f32;
-    };
+/// 拖尾顶点: 位置 + 颜色 + uv
+pub fn trail_vertex_buffer_desc(id: String) -> VertexBufferDesc {
+    VertexBufferDesc::vertices(
+        id.into(),
+        None,
+        vec![
+            VertexAttribute {
+                kind: EVertexDataKind::Position,
+                format: VertexFormat::Float32x3,
+            },
+            VertexAttribute {
+                kind: EVertexDataKind::Color4,
+                format: VertexFormat::Float32x4,
+            },
+            VertexAttribute {
+                kind: EVertexDataKind::UV,
+                format: VertexFormat::Float32x2,
+            },
+        ],
+    )
+}
 
-    let mut radius_thickness = 0.0;
-    if let Some(v) = config.get("radiusThickness") {
-        radius_thickness = v.as_f64().unwrap() as f32;
-    };
+pub fn trail_world_space(config: &Value) -> bool {
+    from_json::<TrailJson>(config)
+        .ok()
+        .and_then(|trail| trail.world_space)
+        .map(|v| v.0)
+        .unwrap_or(false)
+}
 
-    let mut arc = IShapeArc::default();
-    if let Some(v) = config.get("arc") {
-        let value = v["value"].as_f64().unwrap() as f32;
-        let spread = v["spread"].as_f64().unwrap() as f32;
-        let speed = v["speed"].as_f64().unwrap() as f32;
-
-        arc = match v["mode"].as_i64().unwrap() {
-            1 => IShapeArc::IShapeArcRandom(IShapeArcRandom {
-                mode: EShapeEmitterArcMode::Random,
-                value,
-                spread,
-                speed,
-            }),
-            2 => IShapeArc::IShapeArcLoop(IShapeArcLoop {
-                mode: EShapeEmitterArcMode::Loop,
-                value: v["mode"].as_f64().unwrap() as f32,
-                spread: v["spread"].as_f64().unwrap() as f32,
-                speed: v["speed"].as_f64().unwrap() as f32,
-            }),
-            3 => IShapeArc::IShapeArcPingPong(IShapeArcPingPong {
-                mode: EShapeEmitterArcMode::PingPong,
-                value: v["mode"].as_f64().unwrap() as f32,
-                spread: v["spread"].as_f64().unwrap() as f32,
-                speed: v["speed"].as_f64().unwrap() as f32,
-            }),
-            4 => IShapeArc::IShapeArcBurstSpread(IShapeArcBurstSpread {
-                mode: EShapeEmitterArcMode::BurstsSpread,
-                value: v["mode"].as_f64().unwrap() as f32,
-                spread: v["spread"].as_f64().unwrap() as f32,
-                speed: v["speed"].as_f64().unwrap() as f32,
-            }),
-            _ => panic!("arc mode is not exits"),
-        }
-    };
+/// 编辑器导出的开关为 0 / 1
+fn bool_json(value: bool) -> Value {
+    Value::from(if value { 1 } else { 0 })
+}
 
-    let mut scale = None;
-    if let Some(v) = config.get("scale") {
-        scale = Some([
-            v[0].as_f64().unwrap() as f32,
-            v[1].as_f64().unwrap() as f32,
-            v[2].as_f64().unwrap() as f32,
-        ]);
-    };
+/// IParticleSystemConfig 转换为 meshParticle extras, 与 gltf_format_particle_cfg 互逆
+///   * Option 为 None 的项不输出
+///   * ERenderMode::None 输出为 5
+///   * bursts 已合并到 emission, 按 emission 输出
+///   * 不在 IParticleSystemConfig 中的项不输出, 保存时需保留原配置中的值
+///     * playOnAwake / material / subEmitters / vertexStreams / sortMode / minParticleSize / maxParticleSize
+///     * 拖尾的 material
+pub fn gltf_particle_cfg_json(config: &IParticleSystemConfig) -> Value {
+    let mut result = Value::Object(Default::default());
+
+    result["name"] = Value::from(config.name.as_str());
+    result["duration"] = number_json(config.duration);
+    result["startDelay"] = number_json(config.start_delay);
+    result["looping"] = Value::from(config.looping);
+    result["prewarm"] = bool_json(config.prewarm);
+    result["simulationSpaceIsWorld"] = Value::from(match config.simulation_space_is_world {
+        EMeshParticleSpaceMode::Local => 0,
+        EMeshParticleSpaceMode::World => 1,
+    });
+    result["scalingMode"] = Value::from(match config.scaling_mode {
+        EMeshParticleScaleMode::Hierarchy => 0,
+        EMeshParticleScaleMode::Local => 1,
+        EMeshParticleScaleMode::Shape => 2,
+    });
+    result["renderAlignment"] = Value::from(match config.render_alignment {
+        ERenderAlignment::View => 0,
+        ERenderAlignment::World => 1,
+        ERenderAlignment::Velocity => 2,
+        ERenderAlignment::Facing => 3,
+        ERenderAlignment::Local => 4,
+    });
+    result["renderMode"] = Value::from(match config.render_mode {
+        ERenderMode::Billboard => 0,
+        ERenderMode::StretchedBillboard => 1,
+        ERenderMode::HorizontalBillboard => 2,
+        ERenderMode::VerticalBillboard => 3,
+        ERenderMode::Mesh => 4,
+        ERenderMode::None => 5,
+    });
+    result["stretchedLengthScale"] = number_json(config.stretched_length_scale);
+    result["stretchedVelocityScale"] = number_json(config.stretched_velocity_scale);
+    if let Some(v) = &config.render_pivot {
+        result["renderPivot"] = numbers_json(v);
+    }
+    result["maxParticles"] = number_json(config.max_particles);
+    result["startSpeed"] = one_param_info_json(&config.start_speed);
+    result["lifetime"] = one_param_info_json(&config.lifetime);
+    result["startColor"] = four_gradient_info_json(&config.start_color);
+    result["startSize"] = param_info_json(&config.start_size);
+    result["startRotation"] = param_info_json(&config.start_rotation);
+    result["gravity"] = one_param_info_json(&config.gravity);
+
+    let (rate, bursts) = &config.emission;
+    let mut emission = vec![number_json(*rate)];
+    if let Some(bursts) = bursts {
+        emission.push(Value::Array(bursts.iter().map(|v| numbers_json(v)).collect()));
+    }
+    result["emission"] = Value::Array(emission);
+
+    result["shape"] = shape_json(&config.shape);
+
+    if let Some(v) = &config.velocity_over_lifetime {
+        result["velocityOverLifetime"] = param_info_json(v);
+    }
+    if let Some(v) = config.velocity_over_lifetime_is_local {
+        result["velocityOverLifetimeIsLocal"] = Value::from(v);
+    }
+    if let Some(v) = &config.limit_velocity_over_lifetime {
+        result["limitVelocityOverLifetime"] = one_param_info_json(v);
+    }
+    if let Some(v) = config.limit_velocity_over_lifetime_dampen {
+        result["limitVelocityOverLifetimeDampen"] = number_json(v);
+    }
+    if let Some(v) = &config.force_over_lifetime {
+        result["forceOverLifetime"] = param_info_json(v);
+    }
+    if let Some(v) = config.force_space_is_local {
+        result["forceSpaceIsLocal"] = Value::from(v);
+    }
+    if let Some(v) = &config.color_over_lifetime {
+        result["colorOverLifetime"] = four_gradient_info_json(v);
+    }
+    if let Some((v, min, max)) = &config.color_by_speed {
+        result["colorBySpeed"] =
+            Value::Array(vec![four_gradient_info_json(v), number_json(*min), number_json(*max)]);
+    }
+    if let Some(v) = &config.size_over_lifetime {
+        result["sizeOverLifetime"] = param_info_json(v);
+    }
+    if let Some((v, min, max)) = &config.size_by_speed {
+        result["sizeBySpeed"] =
+            Value::Array(vec![one_param_info_json(v), number_json(*min), number_json(*max)]);
+    }
+    if let Some(v) = &config.rotation_over_lifetime {
+        result["rotationOverLifetime"] = param_info_json(v);
+    }
+    if let Some((v, min, max)) = &config.rotation_by_speed {
+        result["rotationBySpeed"] =
+            Value::Array(vec![one_param_info_json(v), number_json(*min), number_json(*max)]);
+    }
+    if let Some(v) = &config.texture_sheet {
+        result["textureSheet"] = texture_sheet_json(v);
+    }
+    if let Some(v) = &config.texture {
+        result["texture"] = Value::from(v.as_str());
+    }
+    if let Some(v) = &config.trail {
+        result["trail"] = trail_json(v);
+    }
+    if let Some(v) = &config.orbtial_velocity {
+        result["orbtialVelocity"] = param_info_json(v);
+    }
+    if let Some(v) = &config.orbital_offset {
+        result["orbitalOffset"] = param_info_json(v);
+    }
+    if let Some(v) = &config.orbital_radial {
+        result["orbitalRadial"] = one_param_info_json(v);
+    }
+    if let Some(v) = &config.speed_modifier {
+        result["speedModifier"] = one_param_info_json(v);
+    }
+    if let Some(v) = &config.custom1 {
+        result["custom1"] = Value::Array(v.iter().map(one_param_info_json).collect());
+    }
 
-    let mut position = None;
-    if let Some(v) = config.get("position") {
-        position = Some([
-            v[0].as_f64().unwrap() as f32,
-            v[1].as_f64().unwrap() as f32,
-            v[2].as_f64().unwrap() as f32,
-        ]);
-    };
+    result
+}
 
-    let mut rotation = None;
-    if let Some(v) = config.get("rotation") {
-        rotation = Some([
-            v[0].as_f64().unwrap() as f32,
-            v[1].as_f64().unwrap() as f32,
-            v[2].as_f64().unwrap() as f32,
-        ]);
+/// mode 0 (默认 arc) 解析后按其实际类型输出
+fn arc_json(arc: &IShapeArc) -> Value {
+    let (mode, value, spread, speed) = match arc {
+        IShapeArc::IShapeArcRandom(v) => (1, v.value, v.spread, v.speed),
+        IShapeArc::IShapeArcLoop(v) => (2, v.value, v.spread, v.speed),
+        IShapeArc::IShapeArcPingPong(v) => (3, v.value, v.spread, v.speed),
+        IShapeArc::IShapeArcBurstSpread(v) => (4, v.value, v.spread, v.speed),
     };
 
-    let mut align_dir = 0;
-    if let Some(v) = config.get("alignDir") {
-        align_dir = v.as_i64().unwrap() as u32;
-    };
+    let mut result = Value::Object(Default::default());
+    result["mode"] = Value::from(mode);
+    result["value"] = number_json(value);
+    result["spread"] = number_json(spread);
+    result["speed"] = number_json(speed);
+    result
+}
 
-    let mut angle = 0.0;
-    if let Some(v) = config.get("angle") {
-        angle = v.as_f64().unwrap() as f32;
+fn shape_json(shape: &IShape) -> Value {
+    let mut result = Value::Object(Default::default());
+
+    let (scale, position, rotation, align_dir, randomize) = match shape {
+        IShape::ShapeCone(v) => {
+            result["type"] = Value::from(0);
+            result["radius"] = number_json(v.radius);
+            result["angle"] = number_json(v.angle);
+            result["radiusThickness"] = number_json(v.radius_thickness);
+            result["arc"] = arc_json(&v.arc);
+            result["emit_as_volume"] = bool_json(v.emit_as_volume);
+            result["height"] = number_json(v.height);
+            (&v.scale, &v.position, &v.rotation, v.align_dir, &v.randomize)
+        }
+        IShape::ShapeSphere(v) => {
+            result["type"] = Value::from(1);
+            result["radius"] = number_json(v.radius);
+            result["radiusThickness"] = number_json(v.radius_thickness);
+            result["arc"] = arc_json(&v.arc);
+            (&v.scale, &v.position, &v.rotation, v.align_dir, &v.randomize)
+        }
+        IShape::ShapeBox(v) => {
+            result["type"] = Value::from(2);
+            result["is_volume"] = Value::from(v.is_volume);
+            if let Some(mode) = &v.box_emit_mode {
+                result["box_emit_mode"] = Value::from(match mode {
+                    EBoxShapeMode::Volume => 0,
+                    EBoxShapeMode::Shell => 1,
+                    EBoxShapeMode::Edge => 2,
+                });
+            }
+            (&v.scale, &v.position, &v.rotation, v.align_dir, &v.randomize)
+        }
+        IShape::ShapeCircle(v) => {
+            result["type"] = Value::from(3);
+            result["radius"] = number_json(v.radius);
+            result["radiusThickness"] = number_json(v.radius_thickness);
+            result["arc"] = arc_json(&v.arc);
+            (&v.scale, &v.position, &v.rotation, v.align_dir, &v.randomize)
+        }
+        IShape::ShapeHemisphere(v) => {
+            result["type"] = Value::from(4);
+            result["radius"] = number_json(v.radius);
+            result["radiusThickness"] = number_json(v.radius_thickness);
+            result["arc"] = arc_json(&v.arc);
+            (&v.scale, &v.position, &v.rotation, v.align_dir, &v.randomize)
+        }
+        IShape::ShapeEdge(v) => {
+            result["type"] = Value::from(5);
+            result["radius"] = number_json(v.radius);
+            result["arc"] = arc_json(&v.arc);
+            (&v.scale, &v.position, &v.rotation, v.align_dir, &v.randomize)
+        }
+        IShape::ShapeRectangle(v) => {
+            result["type"] = Value::from(6);
+            (&v.scale, &v.position, &v.rotation, v.align_dir, &v.randomize)
+        }
     };
 
-    let mut randomize = None;
-    if let Some(v) = config.get("randomize") {
-        randomize = Some([
-            v[0].as_f64().unwrap() as f32,
-            v[1].as_f64().unwrap() as f32,
-            v[2].as_f64().unwrap() as f32,
-        ]);
-    };
+    if let Some(v) = scale {
+        result["scale"] = numbers_json(v);
+    }
+    if let Some(v) = position {
+        result["position"] = numbers_json(v);
+    }
+    if let Some(v) = rotation {
+        result["rotation"] = numbers_json(v);
+    }
+    result["alignDir"] = Value::from(align_dir);
+    if let Some(v) = randomize {
+        result["randomize"] = numbers_json(v);
+    }
 
-    let mut emit_as_volume = true;
-    if let Some(vemit_as_volume) = config.get("emit_as_volume") {
-        if vemit_as_volume.as_i64().unwrap() == 0 {
-            emit_as_volume = false;
-        }
-    };
+    result
+}
 
-    let mut is_volume = 0;
-    if let Some(v) = config.get("is_volume") {
-        is_volume = v.as_i64().unwrap() as u32;
-    };
+fn texture_sheet_json(sheet: &ITextureSheet) -> Value {
+    let mut result = Value::Object(Default::default());
+    result["frameOverTime"] = one_param_info_json(&sheet.frame_over_time);
+    result["animMode"] = Value::from(match sheet.anim_mode {
+        AnimationMode::WholeSheet => 0,
+        AnimationMode::SingleRow => 1,
+    });
+    result["customRow"] = number_json(sheet.custom_row);
+    result["cycles"] = number_json(sheet.cycles);
+    result["rowMode"] = Value::from(match sheet.row_mode {
+        RowMode::Custom => 0,
+        RowMode::Random => 1,
+    });
+    result["startFrame"] = one_param_info_json(&sheet.start_frame);
+    result["tilesX"] = number_json(sheet.tiles_x);
+    result["tilesY"] = number_json(sheet.tiles_y);
+    result["timeMode"] = Value::from(match sheet.time_mode {
+        TimeMode::Liftime => 0,
+        TimeMode::Speed => 1,
+    });
+    result
+}
 
-    let mut box_emit_mode = None;
-    if let Some(v) = config.get("box_emit_mode") {
-        match v.as_i64().unwrap() {
-            0 => box_emit_mode = Some(EBoxShapeMode::Volume),
-            1 => box_emit_mode = Some(EBoxShapeMode::Shell),
-            2 => box_emit_mode = Some(EBoxShapeMode::Edge),
-            _ => panic!("box_emit_mode is not exits"),
-        }
-    };
+fn trail_json(trail: &ITrail) -> Value {
+    let mut result = Value::Object(Default::default());
+    result["ratio"] = number_json(trail.ratio);
+    result["mode"] = Value::from(match trail.mode {
+        ETrailMode::Particles => 0,
+        ETrailMode::Ribbon => 1,
+    });
+    result["lifetime"] = one_param_info_json(&trail.lifetime);
+    result["ribbonCount"] = number_json(trail.ribbon_count);
+    result["attachRibbonsToTransform"] = bool_json(trail.attach_ribbons_to_transform);
+    result["minDist"] = number_json(trail.min_dist);
+    result["worldSpace"] = bool_json(trail.world_space);
+    result["dieWithParticle"] = bool_json(trail.die_with_particle);
+    result["sizeAffectsWidth"] = bool_json(trail.size_affects_width);
+    result["textureMode"] = Value::from(match trail.texture_mode {
+        ETrailTextureMode::Stretch => 0,
+        ETrailTextureMode::Tile => 1,
+        ETrailTextureMode::DistributePerSegment => 2,
+        ETrailTextureMode::RepeatPerSegment => 3,
+    });
+    result["sizeAffectsLifetime"] = bool_json(trail.size_affects_lifetime);
+    result["inheritParticleColor"] = bool_json(trail.inherit_particle_color);
+    result["colorOverLifetime"] = four_gradient_info_json(&trail.color_over_lifetime);
+    result["widthOverTrail"] = one_param_info_json(&trail.width_over_trail);
+    result["colorOverTrail"] = four_gradient_info_json(&trail.color_over_trail);
+    result
+}
 
-    match config["type"].as_i64().unwrap() {
-        0 => IShape::ShapeCone(IShapeCone {
-            _type: 0,
-            radius,
-            angle,
-            radius_thickness,
-            arc,
-            emit_as_volume,
-            height,
-            scale,
-            position,
-            rotation,
-            align_dir,
-            randomize,
-        }),
-        1 => IShape::ShapeSphere(IShapeSphere {
-            _type: 1,
-            radius,
-            radius_thickness,
-            arc,
-            scale,
-            position,
-            rotation,
-            align_dir,
-            randomize,
-        }),
-        2 => IShape::ShapeBox(IShapeBox {
-            _type: 2,
-            scale,
-            position,
-            rotation,
-            align_dir,
-            randomize,
-            is_volume,
-            box_emit_mode,
-        }),
-        3 => IShape::ShapeCircle(IShapeCircle {
-            _type: 3,
-            radius,
-            radius_thickness,
-            arc,
-            scale,
-            position,
-            rotation,
-            align_dir,
-            randomize,
-        }),
-        4 => IShape::ShapeHemisphere(IShapeHemisphere {
-            _type: 4,
-            radius,
-            radius_thickness,
-            arc,
-            scale,
-            position,
-            rotation,
-            align_dir,
-            randomize,
-        }),
-        5 => IShape::ShapeEdge(IShapeEdge {
-            _type: 5,
-            radius,
-            arc,
-            scale,
-            position,
-            rotation,
-            align_dir,
-            randomize,
-        }),
-        6 => IShape::ShapeRectangle(IShapeRectangle {
-            _type: 6,
-            scale,
-            position,
-            rotation,
-            align_dir,
-            randomize,
-        }),
-
-        _ => {
-            panic!("config of FourGradientInfo: {} is exits!!!!", config)
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_util::json;
+
+    #[test]
+    fn test_particle_cfg_round_trip() {
+        let sample = json(
+            r#"{
+                "name": "fire",
+                "duration": 2.0,
+                "startDelay": 0.5,
+                "looping": 1,
+                "prewarm": 0,
+                "simulationSpaceIsWorld": 1,
+                "scalingMode": 2,
+                "renderAlignment": 3,
+                "renderMode": 1,
+                "stretchedLengthScale": 2.0,
+                "stretchedVelocityScale": 0.0,
+                "renderPivot": [0.0, 0.5, 0.0],
+                "maxParticles": 100.0,
+                "startSpeed": [1, 2, 1.0, 4.0],
+                "lifetime": [1, 1, 2.0],
+                "startColor": [4, 1, [1.0, 0.5, 0.25, 1.0]],
+                "startSize": [3, 1, [1.0, 2.0, 1.0]],
+                "startRotation": [1, 1, 0.0],
+                "gravity": [1, 1, 0.0],
+                "emission": [10.0, [[0.0, 5.0, 1.0, 0.25]]],
+                "shape": {
+                    "type": 0,
+                    "radius": 1.0,
+                    "angle": 25.0,
+                    "radiusThickness": 1.0,
+                    "arc": { "mode": 2, "value": 360.0, "spread": 0.0, "speed": 1.0 },
+                    "emit_as_volume": 0,
+                    "height": 2.0,
+                    "position": [0.0, 1.0, 0.0],
+                    "alignDir": 1
+                },
+                "colorOverLifetime": [4, 4, [[[0.0, 1.0]], [[0.0, 1.0]], [[0.0, 1.0]], [[0.0, 1.0], [1.0, 0.0]]]],
+                "sizeBySpeed": [[1, 1, 1.0], 0.0, 10.0],
+                "textureSheet": {
+                    "frameOverTime": [1, 4, [[[0.0, 0.0, 1.0, 1.0], [1.0, 1.0, 1.0, 1.0]], 16.0]],
+                    "animMode": 0,
+                    "customRow": 0.0,
+                    "cycles": 1.0,
+                    "rowMode": 1,
+                    "startFrame": [1, 1, 0.0],
+                    "tilesX": 4.0,
+                    "tilesY": 4.0,
+                    "timeMode": 0
+                },
+                "texture": "fire.png",
+                "trail": {
+                    "ratio": 1.0,
+                    "mode": 1,
+                    "lifetime": [1, 1, 0.5],
+                    "ribbonCount": 1.0,
+                    "attachRibbonsToTransform": 0,
+                    "minDist": 0.25,
+                    "worldSpace": 1,
+                    "dieWithParticle": 1,
+                    "sizeAffectsWidth": 1,
+                    "textureMode": 1,
+                    "sizeAffectsLifetime": 0,
+                    "inheritParticleColor": 1,
+                    "colorOverLifetime": [4, 1, [1.0, 1.0, 1.0, 1.0]],
+                    "widthOverTrail": [1, 1, 1.0],
+                    "colorOverTrail": [4, 16]
+                },
+                "custom1": [[1, 1, 0.0], [1, 1, 1.0], [1, 1, 0.0], [1, 1, 1.0]]
+            }"#,
+        );
+
+        let mut errors = vec![];
+        let config = gltf_format_particle_cfg(&sample, &mut errors).unwrap();
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert_eq!(gltf_particle_cfg_json(&config), sample);
+    }
+
+    #[test]
+    fn test_particle_cfg_shapes_round_trip() {
+        let shapes = [
+            r#"{ "type": 1, "radius": 1.0, "radiusThickness": 0.5, "arc": { "mode": 1, "value": 90.0, "spread": 0.5, "speed": 2.0 }, "alignDir": 0 }"#,
+            r#"{ "type": 2, "is_volume": 1, "box_emit_mode": 2, "scale": [1.0, 2.0, 1.0], "alignDir": 0 }"#,
+            r#"{ "type": 3, "radius": 1.0, "radiusThickness": 1.0, "arc": { "mode": 3, "value": 180.0, "spread": 0.0, "speed": 1.0 }, "alignDir": 0 }"#,
+            r#"{ "type": 4, "radius": 2.0, "radiusThickness": 1.0, "arc": { "mode": 4, "value": 360.0, "spread": 0.25, "speed": 1.0 }, "alignDir": 0 }"#,
+            r#"{ "type": 5, "radius": 1.0, "arc": { "mode": 1, "value": 360.0, "spread": 0.0, "speed": 1.0 }, "rotation": [0.0, 90.0, 0.0], "alignDir": 0 }"#,
+            r#"{ "type": 6, "randomize": [0.0, 0.5, 0.0], "alignDir": 1 }"#,
+        ];
+        for sample in shapes {
+            let mut config = json(r#"{ "emission": [10.0] }"#);
+            config["shape"] = json(sample);
+            let mut errors = vec![];
+            let parsed = gltf_format_particle_cfg(&config, &mut errors).unwrap();
+            assert!(errors.is_empty(), "{}: {:?}", sample, errors);
+            let exported = gltf_particle_cfg_json(&parsed);
+            assert_eq!(exported["shape"], config["shape"], "{}", sample);
+            assert_eq!(exported["emission"], config["emission"]);
         }
     }
-}
 
-fn format_texture_sheet(config: &Value) -> ITextureSheet {
-    ITextureSheet {
-        frame_over_time: format_one_param_info(&config["frameOverTime"]),
-        anim_mode: match config["animMode"].as_i64().unwrap() {
-            0 => AnimationMode::WholeSheet,
-            1 => AnimationMode::SingleRow,
-            _ => panic!("animMode is not exits"),
-        },
-        custom_row: config["customRow"].as_i64().unwrap() as f32,
-        cycles: config["cycles"].as_i64().unwrap() as f32,
-        row_mode: match config["rowMode"].as_i64().unwrap() {
-            0 => RowMode::Custom,
-            1 => RowMode::Random,
-            _ => panic!("rowMode is not exits"),
-        },
-        start_frame: format_one_param_info(&config["startFrame"]),
-        tiles_x: config["tilesX"].as_i64().unwrap() as f32,
-        tiles_y: config["tilesY"].as_i64().unwrap() as f32,
-        time_mode: match config["timeMode"].as_i64().unwrap() {
-            0 => TimeMode::Liftime,
-            1 => TimeMode::Speed,
-            _ => panic!("timeMode is not exits"),
-        },
+    #[test]
+    fn test_particle_node_with_trail() {
+        let node = json(
+            r#"{
+                "meshParticle": {
+                    "emission": [10.0],
+                    "trail": {
+                        "mode": 1,
+                        "worldSpace": 1,
+                        "textureMode": 3,
+                        "lifetime": [1, 1, 0.5],
+                        "material": 2
+                    }
+                }
+            }"#,
+        );
+        let cfg = &node["meshParticle"];
+
+        let mut errors = vec![];
+        let config = gltf_format_particle_cfg(cfg, &mut errors).unwrap();
+        assert!(errors.is_empty(), "{:?}", errors);
+        let trail = config.trail.as_ref().unwrap();
+        assert!(matches!(trail.mode, ETrailMode::Ribbon));
+        assert!(matches!(trail.texture_mode, ETrailTextureMode::RepeatPerSegment));
+        assert!(trail.world_space);
+        assert!(trail_world_space(&cfg["trail"]));
+        assert_eq!(cfg["trail"]["material"].as_u64(), Some(2));
+
+        let exported = gltf_particle_cfg_json(&config);
+        assert_eq!(exported["trail"]["mode"], json("1"));
+        assert_eq!(exported["trail"]["textureMode"], json("3"));
+    }
+
+    #[test]
+    fn test_particle_cfg_json_dropped() {
+        let sample = json(
+            r#"{
+                "emission": [10.0],
+                "bursts": [{ "time": 0.5, "count": 3, "cycles": 1, "interval": 0.25 }],
+                "playOnAwake": 0,
+                "material": 1,
+                "subEmitters": [{ "node": 2, "type": 0 }],
+                "vertexStreams": ["color"],
+                "sortMode": 1,
+                "trail": { "mode": 1, "material": 0 }
+            }"#,
+        );
+
+        let mut errors = vec![];
+        let config = gltf_format_particle_cfg(&sample, &mut errors).unwrap();
+        assert!(errors.is_empty(), "{:?}", errors);
+        let exported = gltf_particle_cfg_json(&config);
+        assert_eq!(exported["emission"], json("[10.0, [[0.5, 3.0, 1.0, 0.25]]]"));
+        for key in [
+            "bursts",
+            "playOnAwake",
+            "material",
+            "subEmitters",
+            "vertexStreams",
+            "sortMode",
+        ] {
+            assert!(exported.get(key).is_none(), "{}", key);
+        }
+        assert!(exported["trail"].get("material").is_none());
     }
 }
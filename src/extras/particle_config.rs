@@ -0,0 +1,1237 @@
+/// # meshParticle extras 配置
+///   * MeshParticleJson 为 meshParticle 的结构, 转换为 IParticleSystemConfig
+///   * 参数为带标记的数组: [类型, 模式, 数据...]
+///     * 类型: 1 单值 (OneParamInfo), 3 三维 (ThreeParamInfo), 4 颜色 (FourGradient)
+///     * 模式: 1 常量, 2 两常量随机, 4 曲线, 8 两曲线随机, 16 随机颜色 (仅颜色)
+///   * 开关兼容 0 / 1 与 bool
+///   * material: 节点没有 mesh 时, 默认公告板四边形使用的 glTF 材质索引
+///   * bursts: [{ time, count, cycles, interval }], 追加到 emission 的 bursts 之后
+///   * 渲染设置, 配置错误的项输出错误并按未配置处理
+///     * sortMode: 0 不排序, 1 按距离, 2 先发射的在前, 3 后发射的在前
+///     * minParticleSize / maxParticleSize: 粒子屏幕尺寸占视口的比例
+///     * vertexStreams: ["color", "tilloff"], 粒子系统不写入的 custom1 / custom2 / velocity / age 输出提示并忽略
+///   * subEmitters: [{ node, type }], node 为同一 glTF 中的粒子节点, 每个父粒子周期触发一次 (见 particle_control)
+///     * type: 0 birth, 1 death, 2 collision
+///   * 各字段单独解析, 错误的字段记录字段路径并按未配置处理
+///     * 如 "meshParticle.shape.arc.spread: invalid type: ..."
+use std::{fmt, marker::PhantomData};
+
+use particle::{
+    emitter::ishape_emitter_type::{EBoxShapeMode, EShapeEmitterArcMode},
+    iparticle_system_config::{
+        FourGradientInfo, IParticleSystemConfig, IShape, IShapeArc, IShapeArcBurstSpread,
+        IShapeArcLoop, IShapeArcPingPong, IShapeArcRandom, IShapeBox, IShapeCircle, IShapeCone,
+        IShapeEdge, IShapeHemisphere, IShapeRectangle, IShapeSphere, ITextureSheet, ITrail,
+        OneParamInfo, ParamInfo, ThreeParamInfo,
+    },
+    modifier::{
+        texture_sheet::{AnimationMode, RowMode, TimeMode},
+        trail::{ETrailMode, ETrailTextureMode},
+    },
+    particle_system_tool::{
+        EMeshParticleScaleMode, EMeshParticleSpaceMode, ERenderAlignment, ERenderMode,
+    },
+};
+use pi_gltf::json::Value;
+use serde::{
+    de::{self, DeserializeOwned, SeqAccess, Visitor},
+    Deserialize, Deserializer,
+};
+
+use super::{
+    particle::{EParticleSortMode, EParticleVertexStream, ParticleRenderSettings},
+    particle_control::EParticleSubEmitter,
+};
+
+/// 曲线: [[[time, value, inTangent, outTangent], ...], scale]
+pub type CurveJson = (Vec<Vec<f32>>, f32);
+
+/// 渐变: [r, g, b, a] 四个通道, 每个通道为 [[time, value], ...]
+pub type GradientJson = [Vec<[f32; 2]>; 4];
+
+/// meshParticle 不是对象时返回错误, 错误的字段记录到 errors 并使用默认值
+pub fn gltf_format_particle_cfg(
+    mesh_particle_cfg: &Value,
+    errors: &mut Vec<String>,
+) -> Result<IParticleSystemConfig, String> {
+    let config: MeshParticleJson =
+        from_json(mesh_particle_cfg).map_err(|err| format!("meshParticle{}", err))?;
+    let mut field_errors = vec![];
+    let config = config.config(&mut field_errors);
+    errors.extend(field_errors.iter().map(|err| format!("meshParticle.{}", err)));
+    Ok(config)
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubEmitterJson {
+    pub node: usize,
+    #[serde(rename = "type")]
+    pub emitter_type: u32,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SubEmittersJson {
+    #[serde(default)]
+    sub_emitters: Vec<SubEmitterJson>,
+}
+
+/// (触发类型, 子发射器节点索引), 配置错误时输出错误并忽略
+pub fn gltf_particle_sub_emitters(
+    mesh_particle_cfg: &Value,
+) -> Vec<(EParticleSubEmitter, usize)> {
+    let config = match from_json::<SubEmittersJson>(mesh_particle_cfg) {
+        Ok(config) => config,
+        Err(err) => {
+            log::warn!("meshParticle{}", err);
+            return vec![];
+        }
+    };
+
+    let mut result = vec![];
+    for (index, v) in config.sub_emitters.iter().enumerate() {
+        match EParticleSubEmitter::from_json(v.emitter_type) {
+            Ok(EParticleSubEmitter::Collision) => {
+                log::warn!("meshParticle.subEmitters[{}]: collision is not supported", index)
+            }
+            Ok(kind) => result.push((kind, v.node)),
+            Err(err) => log::warn!("meshParticle.subEmitters[{}].type: {}", index, err),
+        }
+    }
+    result
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ParticleRenderJson {
+    sort_mode: Option<Value>,
+    min_particle_size: Option<Value>,
+    max_particle_size: Option<Value>,
+    vertex_streams: Option<Value>,
+}
+
+/// 各项单独解析, 错误的项不影响其它项
+pub fn gltf_particle_render(mesh_particle_cfg: &Value) -> ParticleRenderSettings {
+    let mut result = ParticleRenderSettings::default();
+    let config = match from_json::<ParticleRenderJson>(mesh_particle_cfg) {
+        Ok(config) => config,
+        Err(err) => {
+            log::warn!("meshParticle{}", err);
+            return result;
+        }
+    };
+
+    if let Some(v) = &config.sort_mode {
+        match from_json::<u32>(v) {
+            Ok(v) => match EParticleSortMode::from_json(v) {
+                Ok(v) => result.sort = v,
+                Err(err) => log::warn!("meshParticle.sortMode: {}", err),
+            },
+            Err(err) => log::warn!("meshParticle.sortMode{}", err),
+        }
+    }
+    if let Some(v) = &config.min_particle_size {
+        match from_json::<f32>(v) {
+            Ok(v) => result.min_size = v,
+            Err(err) => log::warn!("meshParticle.minParticleSize{}", err),
+        }
+    }
+    if let Some(v) = &config.max_particle_size {
+        match from_json::<f32>(v) {
+            Ok(v) => result.max_size = v,
+            Err(err) => log::warn!("meshParticle.maxParticleSize{}", err),
+        }
+    }
+    if let Some(v) = &config.vertex_streams {
+        match from_json::<Vec<String>>(v) {
+            Ok(names) => {
+                result.streams.clear();
+                for (index, name) in names.iter().enumerate() {
+                    match EParticleVertexStream::from_json(name) {
+                        Ok(stream) if !result.streams.contains(&stream) => {
+                            result.streams.push(stream)
+                        }
+                        Ok(_) => {}
+                        Err(err) => log::warn!("meshParticle.vertexStreams[{}]: {}", index, err),
+                    }
+                }
+            }
+            Err(err) => log::warn!("meshParticle.vertexStreams{}", err),
+        }
+    }
+
+    result
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ParticlePlayJson {
+    play_on_awake: Option<JsonBool>,
+}
+
+/// playOnAwake 默认为 true
+pub fn gltf_particle_play_on_awake(mesh_particle_cfg: &Value) -> bool {
+    from_json::<ParticlePlayJson>(mesh_particle_cfg)
+        .ok()
+        .and_then(|v| v.play_on_awake)
+        .map(|v| v.0)
+        .unwrap_or(true)
+}
+
+/// 错误格式为 ".路径: 原因", 根节点出错时为 ": 原因", 路径以数组下标开始时为 "[0]: 原因"
+pub fn from_json<'de, T: Deserialize<'de>>(value: &'de Value) -> Result<T, String> {
+    serde_path_to_error::deserialize(value).map_err(|err| {
+        let path = err.path().to_string();
+        if err.path().iter().next().is_none() {
+            format!(": {}", err.inner())
+        } else if path.starts_with('[') {
+            format!("{}: {}", path, err.inner())
+        } else {
+            format!(".{}: {}", path, err.inner())
+        }
+    })
+}
+
+/// 单独解析的字段, 解析错误不影响同级的其它字段
+pub struct Field<T>(pub Result<T, String>);
+
+impl<'de, T: DeserializeOwned> Deserialize<'de> for Field<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = Value::deserialize(deserializer)?;
+        Ok(Field(from_json(&value)))
+    }
+}
+
+/// 错误记录为 "字段路径: 原因", 并按未配置处理
+fn field<T>(name: &str, value: Option<Field<T>>, errors: &mut Vec<String>) -> Option<T> {
+    match value?.0 {
+        Ok(v) => Some(v),
+        Err(err) => {
+            errors.push(format!("{}{}", name, err));
+            None
+        }
+    }
+}
+
+/// 带标记数组中的参数, 第一个元素 (类型) 由 Tagged 读取
+pub trait TaggedParam: Sized {
+    const EXPECTING: &'static str;
+    fn read<'de, A: SeqAccess<'de>>(kind: u32, seq: &mut A) -> Result<Self, A::Error>;
+}
+
+pub struct Tagged<T>(pub T);
+
+impl<'de, T: TaggedParam> Deserialize<'de> for Tagged<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_seq(TaggedVisitor(PhantomData))
+    }
+}
+
+struct TaggedVisitor<T>(PhantomData<T>);
+
+impl<'de, T: TaggedParam> Visitor<'de> for TaggedVisitor<T> {
+    type Value = Tagged<T>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str(T::EXPECTING)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let kind = element(&mut seq, 0)?;
+        T::read(kind, &mut seq).map(Tagged)
+    }
+}
+
+fn element<'de, T: Deserialize<'de>, A: SeqAccess<'de>>(
+    seq: &mut A,
+    index: usize,
+) -> Result<T, A::Error> {
+    seq.next_element()?
+        .ok_or_else(|| de::Error::invalid_length(index, &"tagged param array"))
+}
+
+impl TaggedParam for OneParamInfo {
+    const EXPECTING: &'static str = "OneParamInfo: [1, mode, ...]";
+
+    fn read<'de, A: SeqAccess<'de>>(_kind: u32, seq: &mut A) -> Result<Self, A::Error> {
+        let mode: u32 = element(seq, 1)?;
+        match mode {
+            1 => Ok(OneParamInfo::TInterpolateConstant(element(seq, 2)?)),
+            2 => Ok(OneParamInfo::TInterpolateTwoConstants(
+                element(seq, 2)?,
+                element(seq, 3)?,
+            )),
+            4 => Ok(OneParamInfo::TInterpolateCurve(element(seq, 2)?)),
+            8 => Ok(OneParamInfo::TInterpolateTwoCurves(
+                element(seq, 2)?,
+                element(seq, 3)?,
+            )),
+            _ => Err(de::Error::custom(format!("OneParamInfo mode {} is not exits", mode))),
+        }
+    }
+}
+
+impl TaggedParam for ThreeParamInfo {
+    const EXPECTING: &'static str = "ThreeParamInfo: [3, mode, ...]";
+
+    fn read<'de, A: SeqAccess<'de>>(_kind: u32, seq: &mut A) -> Result<Self, A::Error> {
+        let mode: u32 = element(seq, 1)?;
+        match mode {
+            1 => Ok(ThreeParamInfo::TInterpolateConstant(element(seq, 2)?)),
+            2 => Ok(ThreeParamInfo::TInterpolateTwoConstants(
+                element(seq, 2)?,
+                element(seq, 3)?,
+            )),
+            // x, y, z 三条曲线
+            4 => Ok(ThreeParamInfo::TInterpolateCurve(element(seq, 2)?)),
+            8 => Ok(ThreeParamInfo::TInterpolateTwoCurves(
+                element(seq, 2)?,
+                element(seq, 3)?,
+            )),
+            _ => Err(de::Error::custom(format!("ThreeParamInfo mode {} is not exits", mode))),
+        }
+    }
+}
+
+impl TaggedParam for ParamInfo {
+    const EXPECTING: &'static str = "ParamInfo: [1 | 3, mode, ...]";
+
+    fn read<'de, A: SeqAccess<'de>>(kind: u32, seq: &mut A) -> Result<Self, A::Error> {
+        match kind {
+            1 => OneParamInfo::read(kind, seq).map(ParamInfo::OneParamInfo),
+            3 => ThreeParamInfo::read(kind, seq).map(ParamInfo::ThreeParamInfo),
+            _ => Err(de::Error::custom(format!("ParamInfo type {} is not exits", kind))),
+        }
+    }
+}
+
+impl TaggedParam for FourGradientInfo {
+    const EXPECTING: &'static str = "FourGradientInfo: [4, mode, ...]";
+
+    fn read<'de, A: SeqAccess<'de>>(_kind: u32, seq: &mut A) -> Result<Self, A::Error> {
+        let mode: u32 = element(seq, 1)?;
+        match mode {
+            1 => Ok(FourGradientInfo::TInterpolateColor(element(seq, 2)?)),
+            2 => Ok(FourGradientInfo::TInterpolateTwoColors(
+                element(seq, 2)?,
+                element(seq, 3)?,
+            )),
+            4 => Ok(FourGradientInfo::TInterpolateGradient(element(seq, 2)?)),
+            8 => Ok(FourGradientInfo::TInterpolateTwoGradients(
+                element(seq, 2)?,
+                element(seq, 3)?,
+            )),
+            16 => Ok(FourGradientInfo::TInterpolateRandom),
+            _ => Err(de::Error::custom(format!("FourGradientInfo mode {} is not exits", mode))),
+        }
+    }
+}
+
+/// 编辑器导出的开关为 0 / 1, 也兼容 bool
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct JsonBool(pub bool);
+
+impl<'de> Deserialize<'de> for JsonBool {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(JsonBoolVisitor)
+    }
+}
+
+struct JsonBoolVisitor;
+
+impl<'de> Visitor<'de> for JsonBoolVisitor {
+    type Value = JsonBool;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("bool or 0 / 1")
+    }
+
+    fn visit_bool<E: de::Error>(self, v: bool) -> Result<Self::Value, E> {
+        Ok(JsonBool(v))
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<Self::Value, E> {
+        Ok(JsonBool(v != 0))
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<Self::Value, E> {
+        Ok(JsonBool(v != 0))
+    }
+
+    fn visit_f64<E: de::Error>(self, v: f64) -> Result<Self::Value, E> {
+        Ok(JsonBool(v != 0.))
+    }
+}
+
+/// emission: [rate, [[time, count, cycles, interval], ...]], bursts 可省略
+pub struct EmissionJson(pub f32, pub Option<Vec<[f32; 4]>>);
+
+impl<'de> Deserialize<'de> for EmissionJson {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_seq(EmissionVisitor)
+    }
+}
+
+struct EmissionVisitor;
+
+impl<'de> Visitor<'de> for EmissionVisitor {
+    type Value = EmissionJson;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("emission: [rate, bursts]")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let rate = element(&mut seq, 0)?;
+        let bursts = seq.next_element::<Option<Vec<[f32; 4]>>>()?.flatten();
+        Ok(EmissionJson(rate, bursts))
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BurstJson {
+    pub time: f32,
+    pub count: f32,
+    #[serde(default = "burst_cycles")]
+    pub cycles: f32,
+    #[serde(default)]
+    pub interval: f32,
+}
+
+fn burst_cycles() -> f32 {
+    1.
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArcJson {
+    pub mode: u32,
+    #[serde(default)]
+    pub value: f32,
+    #[serde(default)]
+    pub spread: f32,
+    #[serde(default)]
+    pub speed: f32,
+}
+
+impl ArcJson {
+    /// mode 0 为默认 arc, 不使用 value / spread / speed
+    pub fn arc(&self) -> Result<IShapeArc, String> {
+        let (value, spread, speed) = (self.value, self.spread, self.speed);
+        match self.mode {
+            0 => Ok(IShapeArc::default()),
+            1 => Ok(IShapeArc::IShapeArcRandom(IShapeArcRandom {
+                mode: EShapeEmitterArcMode::Random,
+                value,
+                spread,
+                speed,
+            })),
+            2 => Ok(IShapeArc::IShapeArcLoop(IShapeArcLoop {
+                mode: EShapeEmitterArcMode::Loop,
+                value,
+                spread,
+                speed,
+            })),
+            3 => Ok(IShapeArc::IShapeArcPingPong(IShapeArcPingPong {
+                mode: EShapeEmitterArcMode::PingPong,
+                value,
+                spread,
+                speed,
+            })),
+            4 => Ok(IShapeArc::IShapeArcBurstSpread(IShapeArcBurstSpread {
+                mode: EShapeEmitterArcMode::BurstsSpread,
+                value,
+                spread,
+                speed,
+            })),
+            mode => Err(format!("mode: {} is not exits", mode)),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShapeJson {
+    #[serde(rename = "type")]
+    pub shape_type: u32,
+    pub radius: Option<f32>,
+    pub height: Option<f32>,
+    pub radius_thickness: Option<f32>,
+    pub arc: Option<ArcJson>,
+    pub scale: Option<[f32; 3]>,
+    pub position: Option<[f32; 3]>,
+    pub rotation: Option<[f32; 3]>,
+    pub randomize: Option<[f32; 3]>,
+    pub align_dir: Option<u32>,
+    pub angle: Option<f32>,
+    #[serde(rename = "emit_as_volume")]
+    pub emit_as_volume: Option<JsonBool>,
+    #[serde(rename = "is_volume")]
+    pub is_volume: Option<u32>,
+    #[serde(rename = "box_emit_mode")]
+    pub box_emit_mode: Option<u32>,
+}
+
+impl ShapeJson {
+    pub fn shape(&self) -> Result<IShape, String> {
+        let radius = self.radius.unwrap_or(0.);
+        let height = self.height.unwrap_or(0.);
+        let radius_thickness = self.radius_thickness.unwrap_or(0.);
+        let arc = match &self.arc {
+            Some(arc) => arc.arc().map_err(|err| format!("arc.{}", err))?,
+            None => IShapeArc::default(),
+        };
+        let (scale, position, rotation, randomize) =
+            (self.scale, self.position, self.rotation, self.randomize);
+        let align_dir = self.align_dir.unwrap_or(0);
+        let angle = self.angle.unwrap_or(0.);
+        let emit_as_volume = self.emit_as_volume.map(|v| v.0).unwrap_or(true);
+        let is_volume = self.is_volume.unwrap_or(0);
+        let box_emit_mode = match self.box_emit_mode {
+            None => None,
+            Some(0) => Some(EBoxShapeMode::Volume),
+            Some(1) => Some(EBoxShapeMode::Shell),
+            Some(2) => Some(EBoxShapeMode::Edge),
+            Some(mode) => return Err(format!("box_emit_mode: {} is not exits", mode)),
+        };
+
+        let shape = match self.shape_type {
+            0 => IShape::ShapeCone(IShapeCone {
+                _type: 0,
+                radius,
+                angle,
+                radius_thickness,
+                arc,
+                emit_as_volume,
+                height,
+                scale,
+                position,
+                rotation,
+                align_dir,
+                randomize,
+            }),
+            1 => IShape::ShapeSphere(IShapeSphere {
+                _type: 1,
+                radius,
+                radius_thickness,
+                arc,
+                scale,
+                position,
+                rotation,
+                align_dir,
+                randomize,
+            }),
+            2 => IShape::ShapeBox(IShapeBox {
+                _type: 2,
+                scale,
+                position,
+                rotation,
+                align_dir,
+                randomize,
+                is_volume,
+                box_emit_mode,
+            }),
+            3 => IShape::ShapeCircle(IShapeCircle {
+                _type: 3,
+                radius,
+                radius_thickness,
+                arc,
+                scale,
+                position,
+                rotation,
+                align_dir,
+                randomize,
+            }),
+            4 => IShape::ShapeHemisphere(IShapeHemisphere {
+                _type: 4,
+                radius,
+                radius_thickness,
+                arc,
+                scale,
+                position,
+                rotation,
+                align_dir,
+                randomize,
+            }),
+            5 => IShape::ShapeEdge(IShapeEdge {
+                _type: 5,
+                radius,
+                arc,
+                scale,
+                position,
+                rotation,
+                align_dir,
+                randomize,
+            }),
+            6 => IShape::ShapeRectangle(IShapeRectangle {
+                _type: 6,
+                scale,
+                position,
+                rotation,
+                align_dir,
+                randomize,
+            }),
+            shape_type => return Err(format!("type: {} is not exits", shape_type)),
+        };
+
+        Ok(shape)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TextureSheetJson {
+    pub frame_over_time: Tagged<OneParamInfo>,
+    pub anim_mode: u32,
+    pub custom_row: f32,
+    pub cycles: f32,
+    pub row_mode: u32,
+    pub start_frame: Tagged<OneParamInfo>,
+    pub tiles_x: f32,
+    pub tiles_y: f32,
+    pub time_mode: u32,
+}
+
+impl TextureSheetJson {
+    pub fn texture_sheet(self) -> Result<ITextureSheet, String> {
+        Ok(ITextureSheet {
+            frame_over_time: self.frame_over_time.0,
+            anim_mode: match self.anim_mode {
+                0 => AnimationMode::WholeSheet,
+                1 => AnimationMode::SingleRow,
+                mode => return Err(format!("animMode: {} is not exits", mode)),
+            },
+            custom_row: self.custom_row,
+            cycles: self.cycles,
+            row_mode: match self.row_mode {
+                0 => RowMode::Custom,
+                1 => RowMode::Random,
+                mode => return Err(format!("rowMode: {} is not exits", mode)),
+            },
+            start_frame: self.start_frame.0,
+            tiles_x: self.tiles_x,
+            tiles_y: self.tiles_y,
+            time_mode: match self.time_mode {
+                0 => TimeMode::Liftime,
+                1 => TimeMode::Speed,
+                mode => return Err(format!("timeMode: {} is not exits", mode)),
+            },
+        })
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrailJson {
+    pub ratio: Option<f32>,
+    pub mode: Option<u32>,
+    pub lifetime: Option<Tagged<OneParamInfo>>,
+    pub ribbon_count: Option<f32>,
+    pub attach_ribbons_to_transform: Option<JsonBool>,
+    pub min_dist: Option<f32>,
+    pub world_space: Option<JsonBool>,
+    pub die_with_particle: Option<JsonBool>,
+    pub size_affects_width: Option<JsonBool>,
+    pub texture_mode: Option<u32>,
+    pub size_affects_lifetime: Option<JsonBool>,
+    pub inherit_particle_color: Option<JsonBool>,
+    pub color_over_lifetime: Option<Tagged<FourGradientInfo>>,
+    pub width_over_trail: Option<Tagged<OneParamInfo>>,
+    pub color_over_trail: Option<Tagged<FourGradientInfo>>,
+    /// 拖尾网格使用的 glTF 材质索引, 由 factory 读取
+    pub material: Option<u32>,
+}
+
+impl TrailJson {
+    pub fn trail(self) -> Result<ITrail, String> {
+        let mut trail = ITrail::default();
+
+        if let Some(v) = self.ratio {
+            trail.ratio = v;
+        }
+        if let Some(v) = self.mode {
+            trail.mode = match v {
+                0 => ETrailMode::Particles,
+                1 => ETrailMode::Ribbon,
+                mode => return Err(format!("mode: {} is not exits", mode)),
+            };
+        }
+        if let Some(v) = self.lifetime {
+            trail.lifetime = v.0;
+        }
+        if let Some(v) = self.ribbon_count {
+            trail.ribbon_count = v;
+        }
+        if let Some(v) = self.attach_ribbons_to_transform {
+            trail.attach_ribbons_to_transform = v.0;
+        }
+        if let Some(v) = self.min_dist {
+            trail.min_dist = v;
+        }
+        if let Some(v) = self.world_space {
+            trail.world_space = v.0;
+        }
+        if let Some(v) = self.die_with_particle {
+            trail.die_with_particle = v.0;
+        }
+        if let Some(v) = self.size_affects_width {
+            trail.size_affects_width = v.0;
+        }
+        if let Some(v) = self.texture_mode {
+            trail.texture_mode = match v {
+                0 => ETrailTextureMode::Stretch,
+                1 => ETrailTextureMode::Tile,
+                2 => ETrailTextureMode::DistributePerSegment,
+                3 => ETrailTextureMode::RepeatPerSegment,
+                mode => return Err(format!("textureMode: {} is not exits", mode)),
+            };
+        }
+        if let Some(v) = self.size_affects_lifetime {
+            trail.size_affects_lifetime = v.0;
+        }
+        if let Some(v) = self.inherit_particle_color {
+            trail.inherit_particle_color = v.0;
+        }
+        if let Some(v) = self.color_over_lifetime {
+            trail.color_over_lifetime = v.0;
+        }
+        if let Some(v) = self.width_over_trail {
+            trail.width_over_trail = v.0;
+        }
+        if let Some(v) = self.color_over_trail {
+            trail.color_over_trail = v.0;
+        }
+
+        Ok(trail)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MeshParticleJson {
+    pub name: Option<Field<String>>,
+    pub duration: Option<Field<f32>>,
+    pub start_delay: Option<Field<f32>>,
+    pub looping: Option<Field<u32>>,
+    pub prewarm: Option<Field<JsonBool>>,
+    pub simulation_space_is_world: Option<Field<u32>>,
+    pub scaling_mode: Option<Field<u32>>,
+    pub render_alignment: Option<Field<u32>>,
+    pub render_mode: Option<Field<u32>>,
+    pub stretched_length_scale: Option<Field<f32>>,
+    pub stretched_velocity_scale: Option<Field<f32>>,
+    pub render_pivot: Option<Field<[f32; 3]>>,
+    pub max_particles: Option<Field<f32>>,
+    pub start_speed: Option<Field<Tagged<OneParamInfo>>>,
+    pub lifetime: Option<Field<Tagged<OneParamInfo>>>,
+    pub start_color: Option<Field<Tagged<FourGradientInfo>>>,
+    pub start_size: Option<Field<Tagged<ParamInfo>>>,
+    pub start_rotation: Option<Field<Tagged<ParamInfo>>>,
+    pub gravity: Option<Field<Tagged<OneParamInfo>>>,
+    pub emission: Option<Field<EmissionJson>>,
+    pub bursts: Option<Field<Vec<BurstJson>>>,
+    pub shape: Option<Field<ShapeJson>>,
+    pub velocity_over_lifetime: Option<Field<Tagged<ParamInfo>>>,
+    pub velocity_over_lifetime_is_local: Option<Field<u32>>,
+    pub limit_velocity_over_lifetime: Option<Field<Tagged<OneParamInfo>>>,
+    pub limit_velocity_over_lifetime_dampen: Option<Field<f32>>,
+    pub force_over_lifetime: Option<Field<Tagged<ParamInfo>>>,
+    pub force_space_is_local: Option<Field<u32>>,
+    pub color_over_lifetime: Option<Field<Tagged<FourGradientInfo>>>,
+    /// (颜色, 速度下限, 速度上限)
+    pub color_by_speed: Option<Field<(Tagged<FourGradientInfo>, f32, f32)>>,
+    pub size_over_lifetime: Option<Field<Tagged<ParamInfo>>>,
+    pub size_by_speed: Option<Field<(Tagged<OneParamInfo>, f32, f32)>>,
+    pub rotation_over_lifetime: Option<Field<Tagged<ParamInfo>>>,
+    pub rotation_by_speed: Option<Field<(Tagged<OneParamInfo>, f32, f32)>>,
+    pub texture_sheet: Option<Field<TextureSheetJson>>,
+    pub texture: Option<Field<String>>,
+    pub trail: Option<Field<TrailJson>>,
+    /// 编辑器导出的字段名即为 orbtialVelocity
+    #[serde(rename = "orbtialVelocity")]
+    pub orbtial_velocity: Option<Field<Tagged<ParamInfo>>>,
+    pub orbital_offset: Option<Field<Tagged<ParamInfo>>>,
+    pub orbital_radial: Option<Field<Tagged<OneParamInfo>>>,
+    pub speed_modifier: Option<Field<Tagged<OneParamInfo>>>,
+    pub custom1: Option<Field<[Tagged<OneParamInfo>; 4]>>,
+}
+
+impl MeshParticleJson {
+    /// 错误格式为 "字段: 原因", 错误的字段使用 IParticleSystemConfig 的默认值
+    pub fn config(self, errors: &mut Vec<String>) -> IParticleSystemConfig {
+        let mut config = IParticleSystemConfig::default();
+
+        if let Some(v) = field("name", self.name, errors) {
+            config.name = v;
+        }
+        if let Some(v) = field("duration", self.duration, errors) {
+            config.duration = v;
+        }
+        if let Some(v) = field("startDelay", self.start_delay, errors) {
+            config.start_delay = v;
+        }
+        if let Some(v) = field("looping", self.looping, errors) {
+            config.looping = v;
+        }
+        if let Some(v) = field("prewarm", self.prewarm, errors) {
+            config.prewarm = v.0;
+        }
+        match field("simulationSpaceIsWorld", self.simulation_space_is_world, errors) {
+            Some(0) => config.simulation_space_is_world = EMeshParticleSpaceMode::Local,
+            Some(1) => config.simulation_space_is_world = EMeshParticleSpaceMode::World,
+            Some(v) => errors.push(format!("simulationSpaceIsWorld: {} is not exits", v)),
+            None => {}
+        }
+        match field("scalingMode", self.scaling_mode, errors) {
+            Some(0) => config.scaling_mode = EMeshParticleScaleMode::Hierarchy,
+            Some(1) => config.scaling_mode = EMeshParticleScaleMode::Local,
+            Some(2) => config.scaling_mode = EMeshParticleScaleMode::Shape,
+            Some(v) => errors.push(format!("scalingMode: {} is not exits", v)),
+            None => {}
+        }
+        match field("renderAlignment", self.render_alignment, errors) {
+            Some(0) => config.render_alignment = ERenderAlignment::View,
+            Some(1) => config.render_alignment = ERenderAlignment::World,
+            Some(2) => config.render_alignment = ERenderAlignment::Velocity,
+            Some(3) => config.render_alignment = ERenderAlignment::Facing,
+            Some(4) => config.render_alignment = ERenderAlignment::Local,
+            Some(v) => errors.push(format!("renderAlignment: {} is not exits", v)),
+            None => {}
+        }
+        match field("renderMode", self.render_mode, errors) {
+            Some(0) => config.render_mode = ERenderMode::Billboard,
+            Some(1) => config.render_mode = ERenderMode::StretchedBillboard,
+            Some(2) => config.render_mode = ERenderMode::HorizontalBillboard,
+            Some(3) => config.render_mode = ERenderMode::VerticalBillboard,
+            Some(4) => config.render_mode = ERenderMode::Mesh,
+            Some(5) => config.render_mode = ERenderMode::None,
+            Some(v) => errors.push(format!("renderMode: {} is not exits", v)),
+            None => {}
+        }
+        if let Some(v) = field("stretchedLengthScale", self.stretched_length_scale, errors) {
+            config.stretched_length_scale = v;
+        }
+        if let Some(v) = field("stretchedVelocityScale", self.stretched_velocity_scale, errors) {
+            config.stretched_velocity_scale = v;
+        }
+        if let Some(v) = field("renderPivot", self.render_pivot, errors) {
+            config.render_pivot = Some(v);
+        }
+        if let Some(v) = field("maxParticles", self.max_particles, errors) {
+            config.max_particles = v;
+        }
+        if let Some(v) = field("startSpeed", self.start_speed, errors) {
+            config.start_speed = v.0;
+        }
+        if let Some(v) = field("lifetime", self.lifetime, errors) {
+            config.lifetime = v.0;
+        }
+        if let Some(v) = field("startColor", self.start_color, errors) {
+            config.start_color = v.0;
+        }
+        if let Some(v) = field("startSize", self.start_size, errors) {
+            config.start_size = v.0;
+        }
+        if let Some(v) = field("startRotation", self.start_rotation, errors) {
+            config.start_rotation = v.0;
+        }
+        if let Some(v) = field("gravity", self.gravity, errors) {
+            config.gravity = v.0;
+        }
+        if let Some(EmissionJson(rate, bursts)) = field("emission", self.emission, errors) {
+            config.emission = (rate, bursts);
+        }
+        if let Some(bursts) = field("bursts", self.bursts, errors) {
+            config
+                .emission
+                .1
+                .get_or_insert_with(Vec::new)
+                .extend(bursts.iter().map(|v| [v.time, v.count, v.cycles, v.interval]));
+        }
+        if let Some(v) = field("shape", self.shape, errors) {
+            match v.shape() {
+                Ok(shape) => config.shape = shape,
+                Err(err) => errors.push(format!("shape.{}", err)),
+            }
+        }
+        config.velocity_over_lifetime =
+            field("velocityOverLifetime", self.velocity_over_lifetime, errors).map(|v| v.0);
+        config.velocity_over_lifetime_is_local = field(
+            "velocityOverLifetimeIsLocal",
+            self.velocity_over_lifetime_is_local,
+            errors,
+        );
+        config.limit_velocity_over_lifetime = field(
+            "limitVelocityOverLifetime",
+            self.limit_velocity_over_lifetime,
+            errors,
+        )
+        .map(|v| v.0);
+        config.limit_velocity_over_lifetime_dampen = field(
+            "limitVelocityOverLifetimeDampen",
+            self.limit_velocity_over_lifetime_dampen,
+            errors,
+        );
+        config.force_over_lifetime =
+            field("forceOverLifetime", self.force_over_lifetime, errors).map(|v| v.0);
+        config.force_space_is_local = field("forceSpaceIsLocal", self.force_space_is_local, errors);
+        config.color_over_lifetime =
+            field("colorOverLifetime", self.color_over_lifetime, errors).map(|v| v.0);
+        config.color_by_speed = field("colorBySpeed", self.color_by_speed, errors)
+            .map(|(v, min, max)| (v.0, min, max));
+        config.size_over_lifetime =
+            field("sizeOverLifetime", self.size_over_lifetime, errors).map(|v| v.0);
+        config.size_by_speed = field("sizeBySpeed", self.size_by_speed, errors)
+            .map(|(v, min, max)| (v.0, min, max));
+        config.rotation_over_lifetime =
+            field("rotationOverLifetime", self.rotation_over_lifetime, errors).map(|v| v.0);
+        config.rotation_by_speed = field("rotationBySpeed", self.rotation_by_speed, errors)
+            .map(|(v, min, max)| (v.0, min, max));
+        if let Some(v) = field("textureSheet", self.texture_sheet, errors) {
+            match v.texture_sheet() {
+                Ok(sheet) => config.texture_sheet = Some(sheet),
+                Err(err) => errors.push(format!("textureSheet.{}", err)),
+            }
+        }
+        config.texture = field("texture", self.texture, errors);
+        if let Some(v) = field("trail", self.trail, errors) {
+            match v.trail() {
+                Ok(trail) => config.trail = Some(trail),
+                Err(err) => errors.push(format!("trail.{}", err)),
+            }
+        }
+        config.orbtial_velocity =
+            field("orbtialVelocity", self.orbtial_velocity, errors).map(|v| v.0);
+        config.orbital_offset = field("orbitalOffset", self.orbital_offset, errors).map(|v| v.0);
+        config.orbital_radial = field("orbitalRadial", self.orbital_radial, errors).map(|v| v.0);
+        config.speed_modifier = field("speedModifier", self.speed_modifier, errors).map(|v| v.0);
+        config.custom1 = field("custom1", self.custom1, errors).map(|v| v.map(|v| v.0));
+
+        config
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        extras::particle::{
+            four_gradient_info_json, gltf_particle_cfg_json, one_param_info_json, param_info_json,
+        },
+        test_util::{self, json},
+    };
+
+    fn parse(text: &str) -> (IParticleSystemConfig, Vec<String>) {
+        test_util::parse(text, |config, errors| {
+            gltf_format_particle_cfg(config, errors).unwrap()
+        })
+    }
+
+    fn tagged<T: TaggedParam>(config: &Value) -> T {
+        from_json::<Tagged<T>>(config).unwrap().0
+    }
+
+    fn shape(text: &str) -> Result<IShape, String> {
+        from_json::<ShapeJson>(&json(text))?.shape()
+    }
+
+    fn arc(text: &str) -> Result<IShapeArc, String> {
+        from_json::<ArcJson>(&json(text))?.arc()
+    }
+
+    /// (mode, value, spread, speed)
+    fn arc_params(arc: &IShapeArc) -> (u32, f32, f32, f32) {
+        match arc {
+            IShapeArc::IShapeArcRandom(v) => (1, v.value, v.spread, v.speed),
+            IShapeArc::IShapeArcLoop(v) => (2, v.value, v.spread, v.speed),
+            IShapeArc::IShapeArcPingPong(v) => (3, v.value, v.spread, v.speed),
+            IShapeArc::IShapeArcBurstSpread(v) => (4, v.value, v.spread, v.speed),
+        }
+    }
+
+    #[test]
+    fn test_one_param_info_round_trip() {
+        let samples = [
+            "[1, 1, 0.5]",
+            "[1, 2, 0.5, 2.0]",
+            "[1, 4, [[[0.0, 0.0, 0.0, 1.0], [1.0, 1.0, 1.0, 0.0]], 2.0]]",
+            "[1, 8, [[[0.0, 0.0, 0.0, 0.0], [1.0, 0.5, 0.0, 0.0]], 1.0], [[[0.0, 1.0, 0.0, 0.0]], 4.0]]",
+        ];
+        for sample in samples {
+            let config = json(sample);
+            let info = tagged::<OneParamInfo>(&config);
+            assert_eq!(one_param_info_json(&info), config, "{}", sample);
+        }
+    }
+
+    #[test]
+    fn test_three_param_info_round_trip() {
+        let samples = [
+            "[3, 1, [1.0, 2.0, 3.0]]",
+            "[3, 2, [1.0, 2.0, 3.0], [4.0, 5.0, 6.0]]",
+            "[3, 4, [[[[0.0, 1.0, 0.0, 0.0]], 1.0], [[[0.0, 2.0, 0.0, 0.0]], 1.0], [[[0.5, 3.0, 0.0, 0.0]], 2.0]]]",
+            "[3, 8, [[[[0.0, 1.0, 0.0, 0.0]], 1.0], [[[0.0, 2.0, 0.0, 0.0]], 1.0], [[[0.0, 3.0, 0.0, 0.0]], 1.0]], [[[[1.0, 1.0, 0.0, 0.0]], 0.5], [[[1.0, 2.0, 0.0, 0.0]], 0.5], [[[1.0, 3.0, 0.0, 0.0]], 0.5]]]",
+        ];
+        for sample in samples {
+            let config = json(sample);
+            let info = tagged::<ParamInfo>(&config);
+            assert_eq!(param_info_json(&info), config, "{}", sample);
+        }
+    }
+
+    #[test]
+    fn test_four_gradient_info_round_trip() {
+        let samples = [
+            "[4, 1, [1.0, 0.5, 0.25, 1.0]]",
+            "[4, 2, [1.0, 0.5, 0.25, 1.0], [0.0, 0.0, 0.0, 0.5]]",
+            "[4, 4, [[[0.0, 1.0], [1.0, 0.0]], [[0.0, 1.0]], [[0.0, 1.0]], [[0.0, 1.0], [0.5, 0.5]]]]",
+            "[4, 8, [[[0.0, 1.0]], [[0.0, 1.0]], [[0.0, 1.0]], [[0.0, 1.0]]], [[[1.0, 0.0]], [[1.0, 0.0]], [[1.0, 0.0]], [[1.0, 0.25]]]]",
+            "[4, 16]",
+        ];
+        for sample in samples {
+            let config = json(sample);
+            let info = tagged::<FourGradientInfo>(&config);
+            assert_eq!(four_gradient_info_json(&info), config, "{}", sample);
+        }
+    }
+
+    #[test]
+    fn test_shape_errors() {
+        assert!(shape(r#"{ "type": 0, "radius": "1" }"#).is_err());
+        assert!(shape(r#"{ "type": 7 }"#).is_err());
+        assert!(shape(r#"{ "radius": 1.0 }"#).is_err());
+        assert!(shape(r#"{ "type": 2, "box_emit_mode": 3 }"#).is_err());
+        assert!(shape(r#"{ "type": 1, "arc": { "mode": 5 } }"#).is_err());
+    }
+
+    #[test]
+    fn test_shape_types() {
+        let table: [(&str, fn(&IShape) -> bool); 7] = [
+            (r#"{ "type": 0, "radius": 1.0, "angle": 25.0 }"#, |v| {
+                matches!(v, IShape::ShapeCone(_))
+            }),
+            (r#"{ "type": 1, "radius": 1.0 }"#, |v| {
+                matches!(v, IShape::ShapeSphere(_))
+            }),
+            (r#"{ "type": 2, "box_emit_mode": 1 }"#, |v| {
+                matches!(v, IShape::ShapeBox(_))
+            }),
+            (r#"{ "type": 3, "radius": 1.0 }"#, |v| {
+                matches!(v, IShape::ShapeCircle(_))
+            }),
+            (r#"{ "type": 4, "radius": 1.0 }"#, |v| {
+                matches!(v, IShape::ShapeHemisphere(_))
+            }),
+            (r#"{ "type": 5, "radius": 1.0 }"#, |v| {
+                matches!(v, IShape::ShapeEdge(_))
+            }),
+            (r#"{ "type": 6, "scale": [1.0, 2.0, 1.0] }"#, |v| {
+                matches!(v, IShape::ShapeRectangle(_))
+            }),
+        ];
+        for (sample, check) in table {
+            let shape = shape(sample).unwrap();
+            assert!(check(&shape), "{}", sample);
+        }
+    }
+
+    #[test]
+    fn test_shape_arc_modes() {
+        let sample = |mode: i64| {
+            format!(
+                r#"{{ "mode": {}, "value": 90.0, "spread": 0.5, "speed": 2.0 }}"#,
+                mode
+            )
+        };
+
+        // mode 0 不使用配置的参数
+        assert_eq!(
+            arc_params(&arc(&sample(0)).unwrap()),
+            arc_params(&IShapeArc::default())
+        );
+        for mode in 1..5 {
+            assert_eq!(
+                arc_params(&arc(&sample(mode)).unwrap()),
+                (mode as u32, 90., 0.5, 2.),
+                "arc mode {}",
+                mode
+            );
+        }
+        assert!(arc(&sample(5)).is_err());
+    }
+
+    #[test]
+    fn test_field_errors() {
+        let error = |text: &str| {
+            let (_, errors) = parse(text);
+            assert_eq!(errors.len(), 1, "{:?}", errors);
+            errors[0].clone()
+        };
+
+        let err = error(r#"{ "shape": { "type": 1, "arc": { "mode": 1, "spread": "a" } } }"#);
+        assert!(err.starts_with("meshParticle.shape.arc.spread: "), "{}", err);
+
+        let err = error(r#"{ "shape": { "type": 1, "arc": { "mode": 5 } } }"#);
+        assert!(err.starts_with("meshParticle.shape.arc.mode: "), "{}", err);
+
+        let err = error(r#"{ "startSpeed": [1, 3, 0.5] }"#);
+        assert!(err.starts_with("meshParticle.startSpeed: "), "{}", err);
+
+        let err = error(r#"{ "startSize": [3, 1, [1.0, "2", 3.0]] }"#);
+        assert!(err.starts_with("meshParticle.startSize[2][1]: "), "{}", err);
+
+        let err = error(r#"{ "trail": { "worldSpace": "yes" } }"#);
+        assert!(err.starts_with("meshParticle.trail.worldSpace: "), "{}", err);
+
+        let err = error(r#"{ "bursts": [{ "time": 0.0 }] }"#);
+        assert!(err.starts_with("meshParticle.bursts[0]: "), "{}", err);
+
+        let err = gltf_format_particle_cfg(&json(r#"[]"#), &mut vec![]).err().unwrap();
+        assert!(err.starts_with("meshParticle: "), "{}", err);
+    }
+
+    #[test]
+    fn test_unknown_enum_values() {
+        let (config, errors) = parse(
+            r#"{
+                "simulationSpaceIsWorld": 2,
+                "scalingMode": 3,
+                "renderAlignment": 9,
+                "renderMode": 7
+            }"#,
+        );
+        assert_eq!(
+            errors,
+            vec![
+                "meshParticle.simulationSpaceIsWorld: 2 is not exits",
+                "meshParticle.scalingMode: 3 is not exits",
+                "meshParticle.renderAlignment: 9 is not exits",
+                "meshParticle.renderMode: 7 is not exits",
+            ]
+        );
+        // 未知的值按未配置处理
+        let exported = gltf_particle_cfg_json(&config);
+        let default = gltf_particle_cfg_json(&IParticleSystemConfig::default());
+        for key in ["simulationSpaceIsWorld", "scalingMode", "renderAlignment", "renderMode"] {
+            assert_eq!(exported[key], default[key], "{}", key);
+        }
+
+        let (config, errors) = parse(r#"{ "renderMode": 5 }"#);
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert!(matches!(config.render_mode, ERenderMode::None));
+    }
+
+    #[test]
+    fn test_field_fallback() {
+        let (config, errors) = parse(
+            r#"{
+                "duration": "long",
+                "looping": 1,
+                "startSpeed": [1, 3, 0.5],
+                "emission": [10.0],
+                "shape": { "type": 7 },
+                "textureSheet": { "animMode": 0 },
+                "texture": "fire.png"
+            }"#,
+        );
+        assert_eq!(errors.len(), 4, "{:?}", errors);
+        assert!(errors[0].starts_with("meshParticle.duration: "), "{}", errors[0]);
+        assert!(errors[1].starts_with("meshParticle.startSpeed: "), "{}", errors[1]);
+        assert!(errors[2].starts_with("meshParticle.shape.type: "), "{}", errors[2]);
+        assert!(errors[3].starts_with("meshParticle.textureSheet: "), "{}", errors[3]);
+
+        // 错误的字段使用默认值, 其它字段正常读取
+        assert_eq!(config.duration, IParticleSystemConfig::default().duration);
+        assert!(config.texture_sheet.is_none());
+        assert_eq!(config.looping, 1);
+        assert_eq!(config.emission.0, 10.0);
+        assert_eq!(config.texture.as_deref(), Some("fire.png"));
+    }
+
+    #[test]
+    fn test_bursts() {
+        let (config, errors) = parse(
+            r#"{
+                "emission": [0.0, [[0.0, 10.0, 1.0, 0.0]]],
+                "bursts": [{ "time": 0.5, "count": 30.0 }, { "time": 1.0, "count": 5.0, "cycles": 3.0, "interval": 0.25 }]
+            }"#,
+        );
+        assert!(errors.is_empty());
+        assert_eq!(
+            config.emission.1.unwrap(),
+            vec![[0.0, 10.0, 1.0, 0.0], [0.5, 30.0, 1.0, 0.0], [1.0, 5.0, 3.0, 0.25]]
+        );
+
+        let (config, _) = parse(r#"{ "bursts": [{ "time": 0.0, "count": 8.0 }] }"#);
+        assert_eq!(config.emission.1.unwrap(), vec![[0.0, 8.0, 1.0, 0.0]]);
+    }
+
+    #[test]
+    fn test_sub_emitters() {
+        let sub_emitters = gltf_particle_sub_emitters(&json(
+            r#"{ "subEmitters": [{ "node": 3, "type": 0 }, { "node": 4, "type": 2 }, { "node": 5, "type": 1 }] }"#,
+        ));
+        assert_eq!(
+            sub_emitters,
+            vec![(EParticleSubEmitter::Birth, 3), (EParticleSubEmitter::Death, 5)]
+        );
+        assert!(gltf_particle_sub_emitters(&json(r#"{}"#)).is_empty());
+        let missing_node = json(r#"{ "subEmitters": [{ "type": 0 }] }"#);
+        assert!(gltf_particle_sub_emitters(&missing_node).is_empty());
+    }
+
+    #[test]
+    fn test_render_settings() {
+        let render = gltf_particle_render(&json(r#"{}"#));
+        assert_eq!(render.sort, EParticleSortMode::None);
+        assert_eq!(
+            render.streams,
+            vec![EParticleVertexStream::Color, EParticleVertexStream::TillOff]
+        );
+
+        let render = gltf_particle_render(&json(
+            r#"{
+                "sortMode": 1,
+                "minParticleSize": 0.01,
+                "maxParticleSize": "big",
+                "vertexStreams": ["tilloff", "velocity", "unknown", "custom1", "tilloff", "age"]
+            }"#,
+        ));
+        assert_eq!(render.sort, EParticleSortMode::Distance);
+        assert_eq!(render.min_size, 0.01);
+        assert_eq!(render.max_size, ParticleRenderSettings::default().max_size);
+        assert_eq!(render.streams, vec![EParticleVertexStream::TillOff]);
+        assert_eq!(render.vertex_buffer_desc().len(), 2);
+
+        let render = gltf_particle_render(&json(r#"{ "sortMode": 7, "vertexStreams": [] }"#));
+        assert_eq!(render.sort, EParticleSortMode::None);
+        assert!(render.streams.is_empty());
+    }
+
+    #[test]
+    fn test_config() {
+        let (config, errors) = parse(
+            r#"{
+                "name": "fire",
+                "duration": 2.0,
+                "looping": 1,
+                "prewarm": 0,
+                "renderMode": 4,
+                "emission": [10.0],
+                "shape": { "type": 0, "radius": 1.0, "angle": 25.0 },
+                "trail": { "mode": 1, "worldSpace": true, "material": 0 }
+            }"#,
+        );
+        assert!(errors.is_empty());
+
+        assert_eq!(config.name, "fire");
+        assert_eq!(config.duration, 2.0);
+        assert_eq!(config.looping, 1);
+        assert!(!config.prewarm);
+        assert!(config.emission.1.is_none());
+        assert!(matches!(config.shape, IShape::ShapeCone(_)));
+        assert!(config.trail.unwrap().world_space);
+    }
+}
@@ -0,0 +1,391 @@
+/// # 粒子播放控制
+///   * 粒子系统加载后不再立即启动, 由 ParticlePlayback 按 startDelay / playOnAwake 控制
+///   * startDelay 由 ParticlePlayback 计时, 传给粒子系统的 start_delay 为 0
+///   * OpsGLTFParticleControl 可作用于 glTF 根节点 (其下所有粒子) 或单个粒子节点
+///   * 非循环的粒子在 startDelay + duration + 最大 lifetime 后结束, 可用 GLTFParticleQuery 查询
+///   * 子发射器引用同一 glTF 中的粒子节点, 不自动播放, 由父粒子按系统时间触发重新播放
+///     * 按父粒子系统的周期触发, 每个周期一次, 在子发射器节点自身的位置播放
+///     * 不跟随单个粒子的出生 / 死亡, 也不在粒子所在位置发射
+///     * Birth: 父粒子开始发射时
+///     * Death: 父粒子第一批粒子达到最大 lifetime 时
+///     * Collision: 暂不支持, 加载时输出提示
+///   * 由 PluginGLTFParticle 注册
+use bevy::{
+    ecs::system::SystemParam,
+    prelude::{Component, Entity, Query, Res, ResMut},
+    time::Time,
+};
+use particle::{
+    iparticle_system_config::{IParticleSystemConfig, OneParamInfo},
+    mesh_particle_system::MeshParticleSystem,
+};
+use pi_engine_shell::prelude::*;
+
+use super::particle::Particle;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EParticlePlayState {
+    /// 等待 startDelay
+    Delay,
+    Playing,
+    Paused,
+    Stopped,
+    Finished,
+}
+
+#[derive(Debug, Clone, Component)]
+pub struct ParticlePlayback {
+    pub state: EParticlePlayState,
+    /// 从播放开始计时, 包含 delay, 单位秒
+    pub time: f32,
+    pub delay: f32,
+    pub duration: f32,
+    pub looping: bool,
+    /// 最后发射的粒子存活的最长时间
+    pub lifetime: f32,
+}
+
+impl ParticlePlayback {
+    pub fn new(config: &IParticleSystemConfig, play_on_awake: bool) -> Self {
+        Self {
+            state: if play_on_awake {
+                EParticlePlayState::Delay
+            } else {
+                EParticlePlayState::Stopped
+            },
+            time: 0.,
+            delay: config.start_delay,
+            duration: config.duration,
+            looping: config.looping != 0,
+            lifetime: max_lifetime(&config.lifetime),
+        }
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.state == EParticlePlayState::Delay || self.state == EParticlePlayState::Playing
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.state == EParticlePlayState::Finished
+    }
+}
+
+/// 曲线取最大值乘以曲线缩放
+fn max_lifetime(info: &OneParamInfo) -> f32 {
+    let curve_max = |curve: &(Vec<Vec<f32>>, f32)| {
+        curve.0.iter().fold(0.0f32, |max, key| {
+            max.max(key.get(1).copied().unwrap_or(0.))
+        }) * curve.1
+    };
+    match info {
+        OneParamInfo::TInterpolateConstant(v) => *v,
+        OneParamInfo::TInterpolateTwoConstants(v1, v2) => v1.max(*v2),
+        OneParamInfo::TInterpolateCurve(curve) => curve_max(curve),
+        OneParamInfo::TInterpolateTwoCurves(curve1, curve2) => {
+            curve_max(curve1).max(curve_max(curve2))
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EParticleSubEmitter {
+    Birth,
+    Death,
+    Collision,
+}
+
+impl EParticleSubEmitter {
+    pub fn from_json(value: u32) -> Result<Self, String> {
+        match value {
+            0 => Ok(Self::Birth),
+            1 => Ok(Self::Death),
+            2 => Ok(Self::Collision),
+            _ => Err(format!("{} is not exits", value)),
+        }
+    }
+}
+
+/// 父粒子节点上记录 (触发类型, 子发射器粒子节点)
+#[derive(Debug, Clone, Component)]
+pub struct ParticleSubEmitters(pub Vec<(EParticleSubEmitter, Entity)>);
+
+/// 到 time 为止的触发次数, 第 n 次触发的时间为 delay + offset + (n - 1) * duration
+fn trigger_count(playback: &ParticlePlayback, offset: f32, time: f32) -> u32 {
+    if time <= playback.delay + offset {
+        0
+    } else if !playback.looping || playback.duration <= 0. {
+        1
+    } else {
+        ((time - playback.delay - offset) / playback.duration) as u32 + 1
+    }
+}
+
+/// glTF 根节点上记录其下所有粒子节点
+#[derive(Debug, Clone, Component)]
+pub struct GLTFParticles(pub Vec<Entity>);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EParticleControl {
+    /// 暂停后继续播放, 停止或结束后重新播放
+    Play,
+    Pause,
+    /// true: 同时清除已发射的粒子
+    Stop(bool),
+    Restart,
+    /// 跳转到指定时间 (秒, 包含 delay) 并继续播放
+    Seek(f32),
+}
+
+/// (glTF 根节点 或 粒子节点, 操作)
+pub struct OpsGLTFParticleControl(pub Entity, pub EParticleControl);
+impl OpsGLTFParticleControl {
+    pub fn ops(target: Entity, control: EParticleControl) -> Self {
+        Self(target, control)
+    }
+}
+pub type ActionListGLTFParticleControl = ActionList<OpsGLTFParticleControl>;
+
+pub fn sys_gltf_particle_control(
+    mut cmds: ResMut<ActionListGLTFParticleControl>,
+    roots: Query<&GLTFParticles>,
+    mut particles: Query<(&mut Particle, &mut ParticlePlayback)>,
+) {
+    cmds.drain().drain(..).for_each(|OpsGLTFParticleControl(target, control)| {
+        let targets = match roots.get(target) {
+            Ok(GLTFParticles(list)) => list.clone(),
+            Err(_) => vec![target],
+        };
+        for entity in targets {
+            match particles.get_mut(entity) {
+                Ok((mut particle, mut playback)) => {
+                    particle_control(&mut particle, &mut playback, control)
+                }
+                Err(_) => log::warn!("particle {:?} not found", entity),
+            }
+        }
+    });
+}
+
+fn particle_control(
+    particle: &mut Particle,
+    playback: &mut ParticlePlayback,
+    control: EParticleControl,
+) {
+    let mp = &mut particle.0;
+    match control {
+        EParticleControl::Play => match playback.state {
+            // 不依赖 start 对暂停状态的处理, 按暂停时的时间重新计算
+            EParticlePlayState::Paused => particle_seek(mp, playback, playback.time),
+            EParticlePlayState::Stopped | EParticlePlayState::Finished => {
+                playback.time = 0.;
+                playback.state = EParticlePlayState::Delay;
+            }
+            EParticlePlayState::Delay | EParticlePlayState::Playing => {}
+        },
+        EParticleControl::Pause => {
+            // Delay 时粒子系统尚未启动, 只停止计时
+            if playback.state == EParticlePlayState::Playing {
+                mp.pause();
+            }
+            if playback.is_playing() {
+                playback.state = EParticlePlayState::Paused;
+            }
+        }
+        EParticleControl::Stop(clear) => {
+            mp.stop();
+            if clear {
+                mp.clear();
+            }
+            playback.state = EParticlePlayState::Stopped;
+        }
+        EParticleControl::Restart => {
+            mp.stop();
+            mp.clear();
+            playback.time = 0.;
+            playback.state = EParticlePlayState::Delay;
+        }
+        EParticleControl::Seek(time) => particle_seek(mp, playback, time),
+    }
+}
+
+/// 清除已发射的粒子, 从头计算到 time 并继续播放
+fn particle_seek(mp: &mut MeshParticleSystem, playback: &mut ParticlePlayback, time: f32) {
+    mp.stop();
+    mp.clear();
+    playback.time = time.max(0.);
+    if playback.time < playback.delay {
+        playback.state = EParticlePlayState::Delay;
+    } else {
+        mp.start();
+        mp.compute(playback.time - playback.delay);
+        playback.state = EParticlePlayState::Playing;
+    }
+}
+
+pub fn sys_gltf_particle_playback(
+    time: Res<Time>,
+    mut particles: Query<(
+        &mut Particle,
+        &mut ParticlePlayback,
+        Option<&ParticleSubEmitters>,
+    )>,
+) {
+    let delta = time.delta_seconds();
+    let mut triggers = vec![];
+    particles.iter_mut().for_each(|(mut particle, mut playback, sub_emitters)| {
+        if !playback.is_playing() {
+            return;
+        }
+
+        let last = playback.time;
+        playback.time += delta;
+
+        if let Some(ParticleSubEmitters(sub_emitters)) = sub_emitters {
+            for (kind, entity) in sub_emitters {
+                let offset = match kind {
+                    EParticleSubEmitter::Birth => 0.,
+                    EParticleSubEmitter::Death => playback.lifetime,
+                    EParticleSubEmitter::Collision => continue,
+                };
+                if trigger_count(&playback, offset, last)
+                    != trigger_count(&playback, offset, playback.time)
+                {
+                    triggers.push(*entity);
+                }
+            }
+        }
+
+        if playback.state == EParticlePlayState::Delay && playback.time >= playback.delay {
+            particle.0.start();
+            playback.state = EParticlePlayState::Playing;
+        }
+
+        if playback.state == EParticlePlayState::Playing
+            && !playback.looping
+            && playback.time >= playback.delay + playback.duration + playback.lifetime
+        {
+            particle.0.stop();
+            playback.state = EParticlePlayState::Finished;
+        }
+    });
+
+    for entity in triggers {
+        if let Ok((mut particle, mut playback, _)) = particles.get_mut(entity) {
+            particle_control(&mut particle, &mut playback, EParticleControl::Restart);
+        }
+    }
+}
+
+#[derive(SystemParam)]
+pub struct GLTFParticleQuery<'w, 's> {
+    pub roots: Query<'w, 's, &'static GLTFParticles>,
+    pub particles: Query<'w, 's, &'static ParticlePlayback>,
+}
+
+impl GLTFParticleQuery<'_, '_> {
+    /// glTF 根节点: 其下所有粒子都结束; 粒子节点: 该粒子结束
+    /// 已停止 (包括未触发的子发射器) 视为结束
+    pub fn is_finished(&self, target: Entity) -> bool {
+        match self.roots.get(target) {
+            Ok(GLTFParticles(list)) => list.iter().all(|entity| self.particle_finished(*entity)),
+            Err(_) => self.particle_finished(target),
+        }
+    }
+
+    fn particle_finished(&self, entity: Entity) -> bool {
+        self.particles
+            .get(entity)
+            .map(|playback| {
+                playback.is_finished() || playback.state == EParticlePlayState::Stopped
+            })
+            .unwrap_or(true)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use particle::extend::format_mesh_particle;
+
+    use super::*;
+
+    fn playback(looping: bool) -> ParticlePlayback {
+        ParticlePlayback {
+            state: EParticlePlayState::Playing,
+            time: 0.,
+            delay: 1.,
+            duration: 2.,
+            looping,
+            lifetime: 0.5,
+        }
+    }
+
+    #[test]
+    fn test_trigger_count() {
+        let once = playback(false);
+        assert_eq!(trigger_count(&once, 0., 0.), 0);
+        assert_eq!(trigger_count(&once, 0., 1.), 0);
+        assert_eq!(trigger_count(&once, 0., 1.1), 1);
+        assert_eq!(trigger_count(&once, 0.5, 1.4), 0);
+        assert_eq!(trigger_count(&once, 0.5, 10.), 1);
+
+        let looping = playback(true);
+        assert_eq!(trigger_count(&looping, 0., 1.1), 1);
+        assert_eq!(trigger_count(&looping, 0., 3.1), 2);
+        assert_eq!(trigger_count(&looping, 0.5, 3.4), 1);
+        assert_eq!(trigger_count(&looping, 0.5, 3.6), 2);
+    }
+
+    #[test]
+    fn test_max_lifetime() {
+        assert_eq!(max_lifetime(&OneParamInfo::TInterpolateConstant(2.)), 2.);
+        assert_eq!(max_lifetime(&OneParamInfo::TInterpolateTwoConstants(1., 3.)), 3.);
+        assert_eq!(
+            max_lifetime(&OneParamInfo::TInterpolateCurve((
+                vec![vec![0., 0.5, 0., 0.], vec![1., 1., 0., 0.]],
+                2.
+            ))),
+            2.
+        );
+        assert_eq!(
+            max_lifetime(&OneParamInfo::TInterpolateTwoCurves(
+                (vec![vec![0., 1., 0., 0.]], 1.),
+                (vec![vec![0., 1., 0., 0.]], 4.)
+            )),
+            4.
+        );
+        // 缺少值的关键帧按 0 处理
+        assert_eq!(
+            max_lifetime(&OneParamInfo::TInterpolateCurve((vec![vec![0.], vec![1., 2.]], 1.))),
+            2.
+        );
+    }
+
+    fn particle() -> Particle {
+        let mut mp = MeshParticleSystem::new();
+        format_mesh_particle(&IParticleSystemConfig::default(), &mut mp);
+        mp.build();
+        Particle(mp)
+    }
+
+    #[test]
+    fn test_pause_resume() {
+        let mut particle = particle();
+
+        let mut playing = playback(false);
+        playing.time = 1.5;
+        particle_control(&mut particle, &mut playing, EParticleControl::Pause);
+        assert_eq!(playing.state, EParticlePlayState::Paused);
+        particle_control(&mut particle, &mut playing, EParticleControl::Play);
+        assert_eq!(playing.state, EParticlePlayState::Playing);
+        assert_eq!(playing.time, 1.5);
+
+        let mut delay = playback(false);
+        delay.state = EParticlePlayState::Delay;
+        delay.time = 0.5;
+        particle_control(&mut particle, &mut delay, EParticleControl::Pause);
+        assert_eq!(delay.state, EParticlePlayState::Paused);
+        particle_control(&mut particle, &mut delay, EParticleControl::Play);
+        assert_eq!(delay.state, EParticlePlayState::Delay);
+        assert_eq!(delay.time, 0.5);
+    }
+}
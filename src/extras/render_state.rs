@@ -0,0 +1,446 @@
+/// # extras 材质的渲染状态
+///   * 各项未配置时不修改, 保持 glTF 材质核心属性设置的状态
+///   * cull: "off" / "front" / "back", 配置后不使用 glTF 材质的 doubleSided
+///   * zWrite: bool
+///   * zTest: 深度比较函数, 未配置时使用引擎默认
+///     * 字符串: "never" / "less" / "equal" / "lequal" / "greater" / "notequal" / "gequal" / "always"
+///     * 数字: 编辑器导出的 WebGL 常量 (0x0200 NEVER ~ 0x0207 ALWAYS)
+///     * bool: false 即不做深度测试 (always)
+///   * alphaMode: 混合模式, 未配置时不修改
+///     * 字符串: "none" / "alpha" / "additive" / "subtract" / "multiply" / "maximized" / "oneone" / "premultiplied"
+///     * 数字: 编辑器导出的 ALPHA_* 常量 (0 不混合, 1 叠加, 2 alpha, 3 减, 4 正片叠底, 5 取最大, 6 ONEONE, 7 预乘)
+///   * stencil*: 暂不支持, 加载时输出提示
+///   * renderQueue: 整数
+///   * 配置错误的项会输出 "材质名.key: 原因", 并按未配置处理
+use bevy::prelude::Entity;
+use pi_engine_shell::prelude::*;
+use pi_gltf::json::Value;
+use pi_render::rhi::{BlendFactor, BlendOperation, CompareFunction};
+use pi_scene_context::prelude::*;
+
+use crate::interface::GLTFAPI;
+
+/// 开启深度测试时引擎使用的比较函数, 切换材质时恢复为此值
+pub const DEFAULT_DEPTH_COMPARE: CompareFunction = CompareFunction::LessEqual;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EExtrasCull {
+    Off,
+    Front,
+    Back,
+}
+
+impl EExtrasCull {
+    pub fn from_json(value: &Value) -> Result<Self, String> {
+        match value.as_str() {
+            Some("off") => Ok(Self::Off),
+            Some("front") => Ok(Self::Front),
+            Some("back") => Ok(Self::Back),
+            _ => Err(format!("unknown cull {}", value)),
+        }
+    }
+
+    pub fn cull_mode(&self) -> CullMode {
+        match self {
+            EExtrasCull::Off => CullMode::Off,
+            EExtrasCull::Front => CullMode::Front,
+            EExtrasCull::Back => CullMode::Back,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EExtrasDepthCompare {
+    Never,
+    Less,
+    Equal,
+    LessEqual,
+    Greater,
+    NotEqual,
+    GreaterEqual,
+    Always,
+}
+
+impl EExtrasDepthCompare {
+    pub fn from_json(value: &Value) -> Result<Self, String> {
+        if let Some(enable) = value.as_bool() {
+            return Ok(if enable { Self::LessEqual } else { Self::Always });
+        }
+
+        if let Some(name) = value.as_str() {
+            return match name {
+                "never" => Ok(Self::Never),
+                "less" => Ok(Self::Less),
+                "equal" => Ok(Self::Equal),
+                "lequal" => Ok(Self::LessEqual),
+                "greater" => Ok(Self::Greater),
+                "notequal" => Ok(Self::NotEqual),
+                "gequal" => Ok(Self::GreaterEqual),
+                "always" => Ok(Self::Always),
+                _ => Err(format!("unknown zTest {}", value)),
+            };
+        }
+
+        match value.as_u64() {
+            Some(0x0200) => Ok(Self::Never),
+            Some(0x0201) => Ok(Self::Less),
+            Some(0x0202) => Ok(Self::Equal),
+            Some(0x0203) => Ok(Self::LessEqual),
+            Some(0x0204) => Ok(Self::Greater),
+            Some(0x0205) => Ok(Self::NotEqual),
+            Some(0x0206) => Ok(Self::GreaterEqual),
+            Some(0x0207) => Ok(Self::Always),
+            _ => Err(format!("unknown zTest {}", value)),
+        }
+    }
+
+    pub fn compare(&self) -> CompareFunction {
+        match self {
+            EExtrasDepthCompare::Never => CompareFunction::Never,
+            EExtrasDepthCompare::Less => CompareFunction::Less,
+            EExtrasDepthCompare::Equal => CompareFunction::Equal,
+            EExtrasDepthCompare::LessEqual => CompareFunction::LessEqual,
+            EExtrasDepthCompare::Greater => CompareFunction::Greater,
+            EExtrasDepthCompare::NotEqual => CompareFunction::NotEqual,
+            EExtrasDepthCompare::GreaterEqual => CompareFunction::GreaterEqual,
+            EExtrasDepthCompare::Always => CompareFunction::Always,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EExtrasBlendMode {
+    None,
+    /// src * src.a + dst * (1 - src.a)
+    Alpha,
+    /// src * src.a + dst
+    Additive,
+    /// dst - src * src.a
+    Subtract,
+    /// src * dst
+    Multiply,
+    /// max(src, dst)
+    Maximized,
+    /// src + dst
+    OneOne,
+    /// src + dst * (1 - src.a)
+    Premultiplied,
+}
+
+impl EExtrasBlendMode {
+    pub fn from_json(value: &Value) -> Result<Self, String> {
+        if let Some(name) = value.as_str() {
+            return match name {
+                "none" => Ok(Self::None),
+                "alpha" | "combine" => Ok(Self::Alpha),
+                "additive" | "add" => Ok(Self::Additive),
+                "subtract" => Ok(Self::Subtract),
+                "multiply" => Ok(Self::Multiply),
+                "maximized" => Ok(Self::Maximized),
+                "oneone" => Ok(Self::OneOne),
+                "premultiplied" => Ok(Self::Premultiplied),
+                _ => Err(format!("unknown alphaMode {}", value)),
+            };
+        }
+
+        match value.as_u64() {
+            Some(0) => Ok(Self::None),
+            Some(1) => Ok(Self::Additive),
+            Some(2) => Ok(Self::Alpha),
+            Some(3) => Ok(Self::Subtract),
+            Some(4) => Ok(Self::Multiply),
+            Some(5) => Ok(Self::Maximized),
+            Some(6) => Ok(Self::OneOne),
+            Some(7) => Ok(Self::Premultiplied),
+            _ => Err(format!("unknown alphaMode {}", value)),
+        }
+    }
+
+    pub fn model_blend(&self) -> ModelBlend {
+        let mut blend = ModelBlend::default();
+        match self {
+            EExtrasBlendMode::None => {
+                blend.enable = false;
+            }
+            EExtrasBlendMode::Alpha => {
+                blend.combine();
+            }
+            EExtrasBlendMode::Additive => {
+                blend.enable = true;
+                blend.src_color = BlendFactor::SrcAlpha;
+                blend.dst_color = BlendFactor::One;
+                blend.src_alpha = BlendFactor::Zero;
+                blend.dst_alpha = BlendFactor::One;
+                blend.opt_color = BlendOperation::Add;
+                blend.opt_alpha = BlendOperation::Add;
+            }
+            EExtrasBlendMode::Subtract => {
+                blend.enable = true;
+                blend.src_color = BlendFactor::SrcAlpha;
+                blend.dst_color = BlendFactor::One;
+                blend.src_alpha = BlendFactor::Zero;
+                blend.dst_alpha = BlendFactor::One;
+                blend.opt_color = BlendOperation::ReverseSubtract;
+                blend.opt_alpha = BlendOperation::Add;
+            }
+            EExtrasBlendMode::Multiply => {
+                blend.enable = true;
+                blend.src_color = BlendFactor::Dst;
+                blend.dst_color = BlendFactor::Zero;
+                blend.src_alpha = BlendFactor::One;
+                blend.dst_alpha = BlendFactor::One;
+                blend.opt_color = BlendOperation::Add;
+                blend.opt_alpha = BlendOperation::Add;
+            }
+            EExtrasBlendMode::Maximized => {
+                // Max 忽略混合因子
+                blend.enable = true;
+                blend.src_color = BlendFactor::One;
+                blend.dst_color = BlendFactor::One;
+                blend.src_alpha = BlendFactor::One;
+                blend.dst_alpha = BlendFactor::One;
+                blend.opt_color = BlendOperation::Max;
+                blend.opt_alpha = BlendOperation::Max;
+            }
+            EExtrasBlendMode::OneOne => {
+                blend.enable = true;
+                blend.src_color = BlendFactor::One;
+                blend.dst_color = BlendFactor::One;
+                blend.src_alpha = BlendFactor::Zero;
+                blend.dst_alpha = BlendFactor::One;
+                blend.opt_color = BlendOperation::Add;
+                blend.opt_alpha = BlendOperation::Add;
+            }
+            EExtrasBlendMode::Premultiplied => {
+                blend.enable = true;
+                blend.src_color = BlendFactor::One;
+                blend.dst_color = BlendFactor::OneMinusSrcAlpha;
+                blend.src_alpha = BlendFactor::One;
+                blend.dst_alpha = BlendFactor::OneMinusSrcAlpha;
+                blend.opt_color = BlendOperation::Add;
+                blend.opt_alpha = BlendOperation::Add;
+            }
+        }
+        blend
+    }
+}
+
+/// 只记录配置了的项, None 为未配置
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ExtrasRenderState {
+    pub cull: Option<EExtrasCull>,
+    pub depth_write: Option<bool>,
+    pub depth_compare: Option<EExtrasDepthCompare>,
+    pub blend: Option<EExtrasBlendMode>,
+    pub render_queue: Option<i32>,
+}
+
+impl ExtrasRenderState {
+    /// 配置错误的项记录到 errors, 并按未配置处理
+    pub fn from_json(info: &Value, errors: &mut Vec<String>) -> Self {
+        let mut result = Self::default();
+
+        if let Some(cull) = info.get("cull") {
+            match EExtrasCull::from_json(cull) {
+                Ok(cull) => result.cull = Some(cull),
+                Err(err) => errors.push(format!("cull: {}", err)),
+            }
+        }
+
+        if let Some(z_write) = info.get("zWrite") {
+            match z_write.as_bool() {
+                Some(z_write) => result.depth_write = Some(z_write),
+                None => errors.push(format!("zWrite: expected bool, found {}", z_write)),
+            }
+        }
+
+        if let Some(z_test) = info.get("zTest") {
+            match EExtrasDepthCompare::from_json(z_test) {
+                Ok(compare) => result.depth_compare = Some(compare),
+                Err(err) => errors.push(format!("zTest: {}", err)),
+            }
+        }
+
+        if let Some(alpha_mode) = info.get("alphaMode") {
+            match EExtrasBlendMode::from_json(alpha_mode) {
+                Ok(blend) => result.blend = Some(blend),
+                Err(err) => errors.push(format!("alphaMode: {}", err)),
+            }
+        }
+
+        if let Some(render_queue) = info.get("renderQueue") {
+            match render_queue.as_i64() {
+                Some(render_queue) => result.render_queue = Some(render_queue as i32),
+                None => errors.push(format!(
+                    "renderQueue: expected integer, found {}",
+                    render_queue
+                )),
+            }
+        }
+
+        if let Some(info) = info.as_object() {
+            info.keys()
+                .filter(|key| key.starts_with("stencil"))
+                .for_each(|key| errors.push(format!("{}: is not supported, ignored", key)));
+        }
+
+        result
+    }
+}
+
+impl GLTFAPI<'_, '_> {
+    pub fn gltf_extras_render_state(&mut self, entity: Entity, name: &str, info: &Value) {
+        let mut errors = vec![];
+        let state = ExtrasRenderState::from_json(info, &mut errors);
+        for err in errors {
+            log::warn!("{}.{}", name, err);
+        }
+
+        if let Some(cull) = state.cull {
+            self.commands
+                .meshcmds
+                .cullmode
+                .push(OpsCullMode::ops(entity, cull.cull_mode()));
+        }
+
+        if let Some(depth_write) = state.depth_write {
+            self.commands
+                .meshcmds
+                .depth_write
+                .push(OpsDepthWrite::ops(entity, depth_write));
+        }
+
+        if let Some(compare) = state.depth_compare {
+            self.commands
+                .meshcmds
+                .depth_compare
+                .push(OpsDepthCompare::ops(entity, compare.compare()));
+        }
+
+        if let Some(blend) = state.blend {
+            self.commands
+                .meshcmds
+                .blend
+                .push(OpsRenderBlend::ops(entity, blend.model_blend()));
+        }
+
+        if let Some(render_queue) = state.render_queue {
+            self.commands
+                .meshcmds
+                .render_queue
+                .push(OpsRenderQueue::ops(entity, 0, render_queue));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_util::{self, json};
+
+    fn parse(text: &str) -> (ExtrasRenderState, Vec<String>) {
+        test_util::parse(text, ExtrasRenderState::from_json)
+    }
+
+    #[test]
+    fn test_render_state_default() {
+        let (state, errors) = parse(r#"{ "alpha": 1 }"#);
+        assert_eq!(state, ExtrasRenderState::default());
+        assert_eq!(state.cull, None);
+        assert_eq!(state.depth_write, None);
+        assert_eq!(state.render_queue, None);
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn test_render_state_sample() {
+        let (state, errors) = parse(
+            r#"{
+                "cull": "back",
+                "zWrite": true,
+                "zTest": "lequal",
+                "alphaMode": "additive",
+                "renderQueue": 3100
+            }"#,
+        );
+        assert!(errors.is_empty());
+        assert_eq!(
+            state,
+            ExtrasRenderState {
+                cull: Some(EExtrasCull::Back),
+                depth_write: Some(true),
+                depth_compare: Some(EExtrasDepthCompare::LessEqual),
+                blend: Some(EExtrasBlendMode::Additive),
+                render_queue: Some(3100),
+            }
+        );
+    }
+
+    #[test]
+    fn test_render_state_editor_numbers() {
+        let (state, errors) = parse(r#"{ "cull": "front", "zTest": 519, "alphaMode": 7 }"#);
+        assert!(errors.is_empty());
+        assert_eq!(state.cull, Some(EExtrasCull::Front));
+        assert_eq!(state.depth_write, None);
+        assert_eq!(state.depth_compare, Some(EExtrasDepthCompare::Always));
+        assert_eq!(state.blend, Some(EExtrasBlendMode::Premultiplied));
+    }
+
+    #[test]
+    fn test_blend_modes() {
+        let table = [
+            ("0", EExtrasBlendMode::None),
+            ("1", EExtrasBlendMode::Additive),
+            ("2", EExtrasBlendMode::Alpha),
+            ("3", EExtrasBlendMode::Subtract),
+            ("4", EExtrasBlendMode::Multiply),
+            ("5", EExtrasBlendMode::Maximized),
+            ("6", EExtrasBlendMode::OneOne),
+            ("7", EExtrasBlendMode::Premultiplied),
+            (r#""none""#, EExtrasBlendMode::None),
+            (r#""alpha""#, EExtrasBlendMode::Alpha),
+            (r#""multiply""#, EExtrasBlendMode::Multiply),
+            (r#""subtract""#, EExtrasBlendMode::Subtract),
+            (r#""maximized""#, EExtrasBlendMode::Maximized),
+        ];
+        for (text, mode) in table {
+            assert_eq!(EExtrasBlendMode::from_json(&json(text)), Ok(mode), "{}", text);
+        }
+    }
+
+    #[test]
+    fn test_blend_operations() {
+        let blend = EExtrasBlendMode::Subtract.model_blend();
+        assert_eq!(blend.opt_color, BlendOperation::ReverseSubtract);
+        let blend = EExtrasBlendMode::Maximized.model_blend();
+        assert_eq!(blend.opt_color, BlendOperation::Max);
+        assert_eq!(blend.opt_alpha, BlendOperation::Max);
+    }
+
+    #[test]
+    fn test_stencil_unsupported() {
+        let (state, errors) = parse(r#"{ "cull": "back", "stencil": true, "stencilRef": 1 }"#);
+        assert_eq!(state.cull, Some(EExtrasCull::Back));
+        assert_eq!(errors.len(), 2);
+        assert!(errors.iter().all(|err| err.ends_with("is not supported, ignored")));
+    }
+
+    #[test]
+    fn test_depth_test_bool() {
+        let (state, _) = parse(r#"{ "zTest": false }"#);
+        assert_eq!(state.depth_compare, Some(EExtrasDepthCompare::Always));
+        let (state, _) = parse(r#"{ "zTest": true }"#);
+        assert_eq!(state.depth_compare, Some(EExtrasDepthCompare::LessEqual));
+    }
+
+    #[test]
+    fn test_render_state_errors() {
+        let (state, errors) = parse(
+            r#"{ "cull": "none", "zWrite": 1, "zTest": "sometimes", "alphaMode": 11 }"#,
+        );
+        assert_eq!(state, ExtrasRenderState::default());
+        assert_eq!(errors.len(), 4);
+        assert!(errors[0].starts_with("cull:"));
+        assert!(errors[3].starts_with("alphaMode:"));
+    }
+}
@@ -0,0 +1,32 @@
+/// # extras 材质使用的 uniform 名
+///   * pi_node_materials / unlit_material 没有导出 key 常量的 uniform 在此定义
+///   * 名称需与 unlit_material 中对应 shader 声明的 uniform 一致
+
+/// two_opacity_mix 的第二张透明度纹理
+pub struct BlockOpacity2Texture;
+impl BlockOpacity2Texture {
+    pub const KEY_TEX: &'static str = "_Opacity2Tex";
+    pub const KEY_TILLOFF: &'static str = "uOpacity2Tilloff";
+    pub const KEY_LEVEL: &'static str = "uOpacity2Level";
+}
+
+/// 第二张透明度纹理的 uv 流动速度
+pub struct BlockOpacity2TextureUVOffsetSpeed;
+impl BlockOpacity2TextureUVOffsetSpeed {
+    pub const KEY_PARAM: &'static str = "uOpacity2UVOS";
+}
+
+/// TwoOpacityMixShader 的两张透明度纹理混合参数
+pub struct BlockTwoOpacityMix;
+impl BlockTwoOpacityMix {
+    pub const KEY_MIX_CONTROL: &'static str = "uMixControl";
+}
+
+/// DistortionUVShader 的扭曲参数
+pub struct BlockDistortionUV;
+impl BlockDistortionUV {
+    /// xy: 扭曲强度, zw: 扭曲流动速度
+    pub const KEY_DISTORTION: &'static str = "uDistortion";
+    pub const KEY_VERTEX_COLOR_FACTOR: &'static str = "uVertexColorFactor";
+    pub const KEY_OPACITY_FROM_MASK_BLUE: &'static str = "uOpacityFromMaskBlue";
+}
@@ -0,0 +1,320 @@
+/// # extras 配置到 uniform 的映射表
+///   * 材质处理函数声明 &[ExtrasUniform], 由 gltf_extras_uniforms 统一读取、检查类型、写入 uniform
+///   * keys 按顺序平分 kind 的分量, 如 vec4(["scale", "offset"]) 中 scale / offset 各占 2 个分量
+///     * 占 1 个分量的 key 的值为 数字 或 bool, 占多个分量的 key 的值为等长的数字数组
+///   * key 缺失时
+///     * 全部缺失: 有 default 则写入 default, 否则跳过该项
+///     * 部分缺失: 有 default 则用 default 对应分量补齐, 否则报告缺失的 key
+///   * default 为写入 uniform 的值, 不经过 transform
+///   * 错误会以 "材质名.key: 原因" 的形式输出
+use bevy::prelude::Entity;
+use pi_atom::Atom;
+use pi_engine_shell::prelude::*;
+use pi_gltf::json::Value;
+use pi_scene_context::prelude::*;
+
+use crate::interface::GLTFAPI;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EExtrasUniformKind {
+    Float,
+    Vec2,
+    Vec4,
+}
+
+impl EExtrasUniformKind {
+    pub const fn count(&self) -> usize {
+        match self {
+            EExtrasUniformKind::Float => 1,
+            EExtrasUniformKind::Vec2 => 2,
+            EExtrasUniformKind::Vec4 => 4,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EExtrasUniformTransform {
+    None,
+    /// k / x
+    Reciprocal(f32),
+}
+
+impl EExtrasUniformTransform {
+    fn apply(&self, value: f32) -> Result<f32, String> {
+        match self {
+            EExtrasUniformTransform::None => Ok(value),
+            EExtrasUniformTransform::Reciprocal(k) => {
+                if value == 0. {
+                    Err(format!("{} / 0 is invalid", k))
+                } else {
+                    Ok(k / value)
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ExtrasUniform {
+    pub keys: &'static [&'static str],
+    pub slot: &'static str,
+    pub kind: EExtrasUniformKind,
+    pub transform: EExtrasUniformTransform,
+    pub default: Option<[f32; 4]>,
+}
+
+impl ExtrasUniform {
+    pub const fn float(keys: &'static [&'static str], slot: &'static str) -> Self {
+        Self::new(keys, slot, EExtrasUniformKind::Float)
+    }
+
+    pub const fn vec2(keys: &'static [&'static str], slot: &'static str) -> Self {
+        Self::new(keys, slot, EExtrasUniformKind::Vec2)
+    }
+
+    pub const fn vec4(keys: &'static [&'static str], slot: &'static str) -> Self {
+        Self::new(keys, slot, EExtrasUniformKind::Vec4)
+    }
+
+    const fn new(
+        keys: &'static [&'static str],
+        slot: &'static str,
+        kind: EExtrasUniformKind,
+    ) -> Self {
+        assert!(
+            !keys.is_empty() && kind.count() % keys.len() == 0,
+            "keys must split the uniform components evenly"
+        );
+        Self {
+            keys,
+            slot,
+            kind,
+            transform: EExtrasUniformTransform::None,
+            default: None,
+        }
+    }
+
+    pub const fn reciprocal(self, k: f32) -> Self {
+        Self {
+            transform: EExtrasUniformTransform::Reciprocal(k),
+            ..self
+        }
+    }
+
+    pub const fn with_default(self, default: [f32; 4]) -> Self {
+        Self {
+            default: Some(default),
+            ..self
+        }
+    }
+
+    /// 每个 key 占用的分量数
+    pub fn key_len(&self) -> usize {
+        self.kind.count() / self.keys.len()
+    }
+
+    /// Ok(None): 所有 key 都未配置且没有默认值
+    pub fn read(&self, info: &Value) -> Result<Option<[f32; 4]>, String> {
+        let key_len = self.key_len();
+        let mut result = [0.; 4];
+        let mut found = false;
+        let mut missing = vec![];
+        for (index, key) in self.keys.iter().enumerate() {
+            let range = index * key_len..(index + 1) * key_len;
+            match info.get(key) {
+                Some(value) => {
+                    found = true;
+                    let values = read_components(value, key_len)
+                        .map_err(|err| format!("{}: {}", key, err))?;
+                    for (i, value) in range.zip(values) {
+                        result[i] = self
+                            .transform
+                            .apply(value)
+                            .map_err(|err| format!("{}: {}", key, err))?;
+                    }
+                }
+                None => match self.default {
+                    Some(default) => result[range.clone()].copy_from_slice(&default[range]),
+                    None => missing.push(*key),
+                },
+            }
+        }
+
+        if !found {
+            return Ok(self.default);
+        }
+
+        if !missing.is_empty() {
+            return Err(format!(
+                "{}: is missing, required with {}",
+                missing.join("+"),
+                self.keys.join("+")
+            ));
+        }
+
+        Ok(Some(result))
+    }
+}
+
+/// 读取 len 个分量: 1 个分量为 数字 / bool, 多个分量为等长的数字数组
+pub(crate) fn read_components(value: &Value, len: usize) -> Result<Vec<f32>, String> {
+    if len == 1 {
+        if let Some(v) = value.as_f64() {
+            return Ok(vec![v as f32]);
+        }
+        if let Some(v) = value.as_bool() {
+            return Ok(vec![if v { 1. } else { 0. }]);
+        }
+        return Err(format!("expected number or bool, found {}", value));
+    }
+
+    let list = match value.as_array() {
+        Some(list) if list.len() == len => list,
+        _ => return Err(format!("expected array of {} numbers, found {}", len, value)),
+    };
+    list.iter()
+        .map(|v| {
+            v.as_f64()
+                .map(|v| v as f32)
+                .ok_or_else(|| format!("expected number in array, found {}", v))
+        })
+        .collect()
+}
+
+impl GLTFAPI<'_, '_> {
+    pub fn gltf_extras_uniforms(
+        &mut self,
+        idmat: Entity,
+        name: &str,
+        info: &Value,
+        uniforms: &[ExtrasUniform],
+    ) {
+        for uniform in uniforms {
+            let value = match uniform.read(info) {
+                Ok(Some(value)) => value,
+                Ok(None) => continue,
+                Err(err) => {
+                    log::warn!("{}.{}", name, err);
+                    continue;
+                }
+            };
+
+            match uniform.kind {
+                EExtrasUniformKind::Float => self.commands.matcmds.float.push(
+                    OpsUniformFloat::ops(idmat, Atom::from(uniform.slot), value[0]),
+                ),
+                EExtrasUniformKind::Vec2 => self.commands.matcmds.vec2.push(
+                    OpsUniformVec2::ops(idmat, Atom::from(uniform.slot), value[0], value[1]),
+                ),
+                EExtrasUniformKind::Vec4 => {
+                    self.commands.matcmds.vec4.push(OpsUniformVec4::ops(
+                        idmat,
+                        Atom::from(uniform.slot),
+                        value[0],
+                        value[1],
+                        value[2],
+                        value[3],
+                    ))
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_util::json;
+
+    #[test]
+    fn test_read_values() {
+        let info = json(
+            r#"{ "alpha": 0.5, "flag": true, "color": [1, 0.5, 0.25, 1], "OU": 2, "OV": 4 }"#,
+        );
+        assert_eq!(
+            ExtrasUniform::float(&["alpha"], "a").read(&info),
+            Ok(Some([0.5, 0., 0., 0.]))
+        );
+        assert_eq!(
+            ExtrasUniform::float(&["flag"], "a").read(&info),
+            Ok(Some([1., 0., 0., 0.]))
+        );
+        assert_eq!(
+            ExtrasUniform::vec4(&["color"], "a").read(&info),
+            Ok(Some([1., 0.5, 0.25, 1.]))
+        );
+        assert_eq!(
+            ExtrasUniform::vec2(&["OU", "OV"], "a").reciprocal(1000.).read(&info),
+            Ok(Some([500., 250., 0., 0.]))
+        );
+    }
+
+    #[test]
+    fn test_read_defaults() {
+        let empty = json("{}");
+        assert_eq!(ExtrasUniform::float(&["alpha"], "a").read(&empty), Ok(None));
+        assert_eq!(
+            ExtrasUniform::vec4(&["x", "y", "sx", "sy"], "a")
+                .with_default([0., 0., 1., 1.])
+                .read(&empty),
+            Ok(Some([0., 0., 1., 1.]))
+        );
+
+        // 缺失的 key 用 default 对应分量补齐, default 不经过 transform
+        let info = json(r#"{ "offset": [0.5, 0.25] }"#);
+        assert_eq!(
+            ExtrasUniform::vec4(&["scale", "offset"], "a")
+                .with_default([1., 1., 0., 0.])
+                .read(&info),
+            Ok(Some([1., 1., 0.5, 0.25]))
+        );
+        let info = json(r#"{ "OV": 4 }"#);
+        assert_eq!(
+            ExtrasUniform::vec2(&["OU", "OV"], "a")
+                .reciprocal(1000.)
+                .with_default([0., 0., 0., 0.])
+                .read(&info),
+            Ok(Some([0., 250., 0., 0.]))
+        );
+    }
+
+    #[test]
+    fn test_read_missing_keys() {
+        let info = json(r#"{ "scale": [1, 1] }"#);
+        let err = ExtrasUniform::vec4(&["scale", "offset"], "a")
+            .read(&info)
+            .unwrap_err();
+        assert!(err.starts_with("offset:"), "{}", err);
+
+        let info = json(r#"{ "OV": 4 }"#);
+        let err = ExtrasUniform::vec2(&["OU", "OV"], "a").read(&info).unwrap_err();
+        assert!(err.starts_with("OU:"), "{}", err);
+    }
+
+    #[test]
+    fn test_read_type_errors() {
+        // 每个 key 占用的分量数固定, 不能由其他 key 补齐
+        let info = json(r#"{ "scale": [1, 1, 1], "offset": [0] }"#);
+        let err = ExtrasUniform::vec4(&["scale", "offset"], "a")
+            .read(&info)
+            .unwrap_err();
+        assert!(err.starts_with("scale:"), "{}", err);
+
+        let info = json(r#"{ "alpha": [1] }"#);
+        assert!(ExtrasUniform::float(&["alpha"], "a").read(&info).is_err());
+        let info = json(r#"{ "color": "red" }"#);
+        assert!(ExtrasUniform::vec4(&["color"], "a").read(&info).is_err());
+        let info = json(r#"{ "color": [1, 1, "a", 1] }"#);
+        assert!(ExtrasUniform::vec4(&["color"], "a").read(&info).is_err());
+    }
+
+    #[test]
+    fn test_read_reciprocal_zero() {
+        let info = json(r#"{ "OU": 0, "OV": 4 }"#);
+        let err = ExtrasUniform::vec2(&["OU", "OV"], "a")
+            .reciprocal(1000.)
+            .read(&info)
+            .unwrap_err();
+        assert!(err.starts_with("OU:"), "{}", err);
+    }
+}
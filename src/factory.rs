@@ -1,6 +1,6 @@
 use std::{
     collections::HashMap,
-    path::PathBuf,
+    path::{Path, PathBuf},
     str::FromStr,
     sync::atomic::{AtomicUsize, Ordering},
 };
@@ -12,8 +12,17 @@ use pi_render::rhi::{IndexFormat, VertexFormat};
 use pi_scene_context::prelude::*;
 
 use crate::{
-    extras::particle::{MeshParticleMeshID, Particle},
-    interface::{GLTFCommands, GLTFAPI},
+    extras::{
+        particle::{trail_world_space, MeshParticleMeshID, MeshParticleTrailID, Particle},
+        particle_config::{gltf_particle_render, gltf_particle_sub_emitters},
+        particle_control::{EParticlePlayState, GLTFParticles, ParticleSubEmitters},
+    },
+    interface::{
+        EGLTFMaterial, EGLTFMaterialKey, GLTFCommands, GLTFMaterialState, GLTFMeshMaterial,
+        GLTFAPI,
+    },
+    texture::{gltf_regist_embedded_images, gltf_regist_texture_urls, GLTFEmbeddedImages},
+    variants::{GLTFMaterialVariants, GLTFVariantMesh},
 };
 
 pub struct GltfLoader {
@@ -71,13 +80,12 @@ impl GltfLoader {
     pub async fn load_buffer_async(&self) -> Vec<(String, Vec<u8>)> {
         let mut buffer_data: Vec<(String, Vec<u8>)> = Vec::new();
         for buffer in self.gltf.buffers() {
-            println!("source: {:?}", buffer.source());
+            log::debug!("source: {:?}", buffer.source());
             match buffer.source() {
                 gltf::buffer::Source::Uri(uri) => {
                     if uri.starts_with("data:") {
                         if let Some(index) = uri.find(',') {
                             let base64_buffer = uri.split_at(index + 1).1;
-                            println!("base64_buffer: {}", base64_buffer);
                             let buffer = base64::decode(base64_buffer).unwrap();
                             buffer_data.push(("".to_string(), buffer));
                         }
@@ -102,14 +110,47 @@ impl GltfLoader {
     }
 }
 
-pub struct OpsGLTFLoaded(pub Entity, pub GltfLoader, pub Vec<(String, Vec<u8>)>);
+/// (场景, gltf, buffer 数据, 根节点), 根节点为 None 时内部创建
+pub struct OpsGLTFLoaded(
+    pub Entity,
+    pub GltfLoader,
+    pub Vec<(String, Vec<u8>)>,
+    pub Option<Entity>,
+);
 impl OpsGLTFLoaded {
     pub fn ops(scene: Entity, loaded: GltfLoader, buffer: Vec<(String, Vec<u8>)>) -> Self {
-        Self(scene, loaded, buffer)
+        Self(scene, loaded, buffer, None)
+    }
+    /// 指定根节点, 之后可通过根节点切换材质变体 (OpsGLTFSelectVariant)
+    pub fn ops_with_root(
+        scene: Entity,
+        root: Entity,
+        loaded: GltfLoader,
+        buffer: Vec<(String, Vec<u8>)>,
+    ) -> Self {
+        Self(scene, loaded, buffer, Some(root))
     }
 }
 pub type ActionListGLTFLoaded = ActionList<OpsGLTFLoaded>;
 
+/// 释放 gltf 时使用, 参数为加载时的 gltf 路径, 清除该 gltf 登记的内嵌图片
+pub struct OpsGLTFRelease(pub PathBuf);
+impl OpsGLTFRelease {
+    pub fn ops(path: PathBuf) -> Self {
+        Self(path)
+    }
+}
+pub type ActionListGLTFRelease = ActionList<OpsGLTFRelease>;
+
+pub fn sys_gltf_release(
+    mut cmds: ResMut<ActionListGLTFRelease>,
+    embedded_images: Res<GLTFEmbeddedImages>,
+) {
+    cmds.drain().drain(..).for_each(|OpsGLTFRelease(path)| {
+        embedded_images.release(&path);
+    });
+}
+
 pub fn sys_gltf_decode(
     mut loadeds: ResMut<ActionListGLTFLoaded>,
     mut cmd: GLTFCommands,
@@ -118,7 +159,7 @@ pub fn sys_gltf_decode(
     loadeds
         .drain()
         .drain(..)
-        .for_each(|OpsGLTFLoaded(scene_id, loader, buffer_data)| {
+        .for_each(|OpsGLTFLoaded(scene_id, loader, buffer_data, root)| {
             let mut factory = GLTFAPI {
                 scene_id,
                 commands: &mut cmd,
@@ -127,16 +168,53 @@ pub fn sys_gltf_decode(
             // let images = gltf.images();
             let root_path = loader._path;
 
+            gltf_regist_embedded_images(
+                &factory.commands.embedded_images,
+                gltf,
+                &root_path,
+                &buffer_data,
+            );
+            gltf_regist_texture_urls(
+                &mut factory.commands.texture_urls,
+                &factory.commands.image_formats,
+                factory.commands.device.features(),
+                gltf,
+                &root_path,
+            );
+
             let mut materials = vec![];
             for material in gltf.materials() {
                 materials.push(material);
             }
             let textures = gltf.textures().collect::<Vec<pi_gltf::Texture>>();
+            // 同一 gltf 中相同材质只创建一次
+            let mut material_map = HashMap::new();
+            let variant_names = match gltf.variants() {
+                Some(variants) => variants.map(|v| v.name().to_string()).collect::<Vec<_>>(),
+                None => vec![],
+            };
+            let mut variant_meshes = vec![];
+            let mut particle_nodes = vec![];
+            // (父粒子节点, [(触发类型, 子发射器节点索引)]), 子发射器节点不自动播放
+            let mut sub_emitters = vec![];
+            let mut sub_emitter_nodes = vec![];
+            for node in gltf.nodes() {
+                if let Some(extras) = node.extras() {
+                    if let Some(mesh_particle_cfg) = extras.get("meshParticle") {
+                        let list = gltf_particle_sub_emitters(mesh_particle_cfg);
+                        sub_emitter_nodes.extend(list.iter().map(|(_, index)| *index));
+                        sub_emitters.push((node.index(), list));
+                    }
+                }
+            }
 
             let mut node_map = HashMap::new();
             let mut node_index = 0;
 
-            let root = commands.spawn_empty().id();
+            let root = match root {
+                Some(root) => root,
+                None => commands.spawn_empty().id(),
+            };
             factory
                 .commands
                 .transformcmds
@@ -179,7 +257,7 @@ pub fn sys_gltf_decode(
                         rotation,
                         scale,
                     } => {
-                        println!(
+                        log::debug!(
                             "====== node translation: {:?}, rotation: {:?}, scale: {:?}",
                             translation, rotation, scale
                         );
@@ -198,9 +276,54 @@ pub fn sys_gltf_decode(
                     t_matrix,
                 );
 
-                println!("node.index(): {}", node.index());
+                log::debug!("node.index(): {}", node.index());
                 node_map.insert(node.index(), node_entity.clone());
 
+                // 每个节点一个粒子系统, 节点 mesh 的所有 primitive 共用粒子实例数据
+                let mut particle_render = None;
+                let mut particle_meshes = vec![];
+                let particle_cfg = match node.extras() {
+                    Some(extras) => extras.get("meshParticle"),
+                    None => None,
+                };
+                let particle = particle_cfg.and_then(|cfg| {
+                    factory.gltf_extras_particle(cfg).map(|mp| (cfg, mp))
+                });
+                if let Some((mesh_particle_cfg, (mp, mut playback))) = particle {
+                    if sub_emitter_nodes.contains(&node.index()) {
+                        playback.state = EParticlePlayState::Stopped;
+                    }
+                    let render = gltf_particle_render(mesh_particle_cfg);
+                    commands
+                        .entity(node_entity)
+                        .insert(Particle(mp))
+                        .insert(playback)
+                        .insert(render.clone());
+                    particle_nodes.push(node_entity);
+                    particle_render = Some(render);
+
+                    if let Some(trail_cfg) = mesh_particle_cfg.get("trail") {
+                        let trail = commands.spawn_empty().id();
+                        let trail_material = gltf_particle_material(
+                            &mut factory,
+                            &mut commands,
+                            &mut material_map,
+                            &materials,
+                            trail_cfg,
+                            &textures,
+                            &root_path,
+                        );
+                        let parent = if trail_world_space(trail_cfg) {
+                            scene_id
+                        } else {
+                            node_entity
+                        };
+                        let id_geo = commands.spawn_empty().id();
+                        factory.gltf_particle_trail(trail, id_geo, parent, &trail_material);
+                        commands.entity(node_entity).insert(MeshParticleTrailID(trail));
+                    }
+                }
+
                 if let Some(mesh) = node.mesh() {
                     // TODO: 取 layer 参数
                     // factory.gltf_layer_mask(entity, layer);
@@ -238,7 +361,7 @@ pub fn sys_gltf_decode(
                             .map(|v| v.into_u32().collect::<Vec<u32>>())
                         {
                             // let indices = indices.iter().map(|v| *v as u16).collect::<Vec<u16>>();
-                            println!("indices: {:?}", indices);
+                            log::debug!("indices: {:?}", indices);
                             let id = format!(
                                 "{:?}; indices:{}{}",
                                 buffer_data[index.load(Ordering::Relaxed)].0,
@@ -263,7 +386,7 @@ pub fn sys_gltf_decode(
                                     .read_positions()
                                     .map(|v| v.collect::<Vec<[f32; 3]>>())
                                 {
-                                    println!("positions: {:?}", positions);
+                                    log::debug!("positions: {:?}", positions);
                                     let id = format!(
                                         "{:?}; positions:{}{}",
                                         buffer_data[index.load(Ordering::Relaxed)].0,
@@ -288,7 +411,7 @@ pub fn sys_gltf_decode(
                                 if let Some(normals) =
                                     reader.read_normals().map(|v| v.collect::<Vec<[f32; 3]>>())
                                 {
-                                    println!("normals: {:?}", normals);
+                                    log::debug!("normals: {:?}", normals);
                                     let id = format!(
                                         "{:?}; normals:{}{}",
                                         buffer_data[index.load(Ordering::Relaxed)].0,
@@ -313,7 +436,7 @@ pub fn sys_gltf_decode(
                                 if let Some(tangents) =
                                     reader.read_tangents().map(|v| v.collect::<Vec<[f32; 4]>>())
                                 {
-                                    println!("tangents: {:?}", tangents);
+                                    log::debug!("tangents: {:?}", tangents);
                                     let id = format!(
                                         "{:?}; tangents:{}{}",
                                         buffer_data[index.load(Ordering::Relaxed)].0,
@@ -339,7 +462,7 @@ pub fn sys_gltf_decode(
                                     .read_colors(set)
                                     .map(|v| v.into_rgba_f32().collect::<Vec<[f32; 4]>>())
                                 {
-                                    println!("colors: {:?}", colors);
+                                    log::debug!("colors: {:?}", colors);
                                     let id = format!(
                                         "{:?}; colors:{}{}",
                                         buffer_data[index.load(Ordering::Relaxed)].0,
@@ -365,7 +488,7 @@ pub fn sys_gltf_decode(
                                     .read_tex_coords(set)
                                     .map(|v| v.into_f32().collect::<Vec<[f32; 2]>>())
                                 {
-                                    println!("uvs{}: {:?}", set, uvs);
+                                    log::debug!("uvs{}: {:?}", set, uvs);
                                     let vertex_data_kind = match set {
                                         0 => EVertexDataKind::UV,
                                         1 => EVertexDataKind::UV2,
@@ -402,7 +525,7 @@ pub fn sys_gltf_decode(
                                     .read_joints(set)
                                     .map(|v| v.into_u16().collect::<Vec<[u16; 4]>>())
                                 {
-                                    println!("joints: {:?}", joints);
+                                    log::debug!("joints: {:?}", joints);
 
                                     let (vertex_data_kind, format) = match set {
                                         0 => (
@@ -450,7 +573,7 @@ pub fn sys_gltf_decode(
                                     .read_weights(set)
                                     .map(|v| v.into_f32().collect::<Vec<[f32; 4]>>())
                                 {
-                                    println!("joints: {:?}", joints);
+                                    log::debug!("joints: {:?}", joints);
 
                                     let (vertex_data_kind, format) = match set {
                                         0 => (
@@ -496,83 +619,70 @@ pub fn sys_gltf_decode(
                             _ => {}
                         });
 
-                        if let Some(material) = primitive
+                        let mesh_material = primitive
                             .material()
                             .index()
-                            .and_then(|i| materials.get(i).cloned())
-                        {
-                            if let Some(extras) = material.extras() {
-                                println!("material extras: {:?}", extras);
-                                let idmat = commands.spawn_empty().id();
-                                factory.gltf_extras_material(
-                                    mesh_entity,
-                                    idmat,
-                                    extras,
+                            .and_then(|i| materials.get(i))
+                            .map(|material| {
+                                gltf_mesh_material(
+                                    &mut factory,
+                                    &mut commands,
+                                    &mut material_map,
+                                    material,
                                     &textures,
                                     &root_path,
-                                );
-
-                                // if let Some(info) = material.emissive_texture() {
-                                //     let tex = info.texture();
-                                //     match tex.source().source() {
-                                //         image::Source::View {
-                                //             view: _,
-                                //             mime_type: _,
-                                //         } => todo!(),
-                                //         image::Source::Uri { uri, mime_type: _ } => {
-                                //             factory.commands.matcmds.texture.push(OpsUniformTexture::ops(
-                                //                 idmat,
-                                //                 UniformTextureWithSamplerParam {
-                                //                     slotname: Atom::from(BlockEmissiveTexture::KEY_TEX),
-                                //                     filter: true,
-                                //                     sample: KeySampler::default(),
-                                //                     url: EKeyTexture::from(uri),
-                                //                 },
-                                //             ));
-                                //         }
-                                //     }
-                                // }
+                                )
+                            });
+                        match &mesh_material {
+                            Some(mesh_material) => {
+                                factory.gltf_apply_material(mesh_entity, mesh_material)
                             }
-                        } else {
-                            factory.gltf_default_material(
-                                mesh_entity.clone(),
-                                commands.spawn_empty().id(),
-                            );
+                            None => factory.gltf_default_material(mesh_entity.clone()),
                         }
 
-                        if let Some(extras) = node.extras() {
-                            if let Some(mesh_particle_cfg) = extras.get("meshParticle") {
-                                let mp = factory.gltf_extras_particle(mesh_particle_cfg);
-                                let mp_entity = node_entity;
-                                commands
-                                    .entity(mp_entity)
-                                    .insert(Particle(mp))
-                                    .insert(MeshParticleMeshID(mesh_entity));
-                                vertex_buffer_desc.push(VertexBufferDesc::instance_world_matrix());
-                                vertex_buffer_desc.push(VertexBufferDesc::instance_color());
-                                vertex_buffer_desc.push(VertexBufferDesc::instance_tilloff());
+                        let mappings = primitive
+                            .mappings()
+                            .map(|mapping| {
+                                let material = gltf_mesh_material(
+                                    &mut factory,
+                                    &mut commands,
+                                    &mut material_map,
+                                    &mapping.material(),
+                                    &textures,
+                                    &root_path,
+                                );
+                                (mapping.variants().to_vec(), material)
+                            })
+                            .collect::<Vec<_>>();
+                        if !mappings.is_empty() {
+                            variant_meshes.push(GLTFVariantMesh {
+                                mesh: mesh_entity,
+                                default: mesh_material,
+                                mappings,
+                            });
+                        }
 
+                        match &particle_render {
+                            Some(render) => {
+                                // 粒子的实例数据为世界空间, 网格挂在场景下
+                                vertex_buffer_desc.extend(render.vertex_buffer_desc());
+                                particle_meshes.push(mesh_entity);
                                 factory
                                     .commands
                                     .transformcmds
                                     .tree
                                     .push(OpsTransformNodeParent::ops(mesh_entity, scene_id));
-                            } else {
+                            }
+                            None => {
                                 factory
                                     .commands
                                     .transformcmds
                                     .tree
                                     .push(OpsTransformNodeParent::ops(mesh_entity, node_entity));
                             }
-                        } else {
-                            factory
-                                .commands
-                                .transformcmds
-                                .tree
-                                .push(OpsTransformNodeParent::ops(mesh_entity, node_entity));
                         }
 
-                        println!("vertex_buffer_desc: {:?}", vertex_buffer_desc);
+                        log::debug!("vertex_buffer_desc: {:?}", vertex_buffer_desc);
                         let id_geo = commands.spawn_empty().id();
                         factory.gltf_geometry(
                             mesh_entity.clone(),
@@ -584,6 +694,27 @@ pub fn sys_gltf_decode(
                         primitives_index += 1;
                     }
                 }
+
+                if let (Some(render), Some(cfg)) = (&particle_render, particle_cfg) {
+                    if particle_meshes.is_empty() {
+                        let mesh = commands.spawn_empty().id();
+                        let id_geo = commands.spawn_empty().id();
+                        let material = gltf_particle_material(
+                            &mut factory,
+                            &mut commands,
+                            &mut material_map,
+                            &materials,
+                            cfg,
+                            &textures,
+                            &root_path,
+                        );
+                        factory.gltf_particle_quad(mesh, id_geo, render, &material);
+                        particle_meshes.push(mesh);
+                    }
+                    commands
+                        .entity(node_entity)
+                        .insert(MeshParticleMeshID(particle_meshes));
+                }
                 if let Some(_skin) = node.skin() {
                     // TODO: 添加姿态
                     // let reader = skin.reader(|buffer| Some(&buffer_data[buffer.index()].1));
@@ -610,7 +741,7 @@ pub fn sys_gltf_decode(
                         let root_bone = bones[0].clone();
                         // bones.push(root_bone);
                         // bones.push(root_bone);
-                        println!("Skin: {:?}, {:?}", entity, bones);
+                        log::debug!("Skin: {:?}, {:?}", entity, bones);
                         let skin = commands.spawn_empty().id();
                         let skin = factory.gltf_create_skin(root_bone, bones, skin);
                         factory.gltf_apply_skin(*entity, skin);
@@ -639,7 +770,7 @@ pub fn sys_gltf_decode(
 
                 for channel in animation.channels() {
                     let node = channel.target().node();
-                    println!("animation!!! node.index(): {}", node.index());
+                    log::debug!("animation!!! node.index(): {}", node.index());
 
                     if let Some(node_id) = node_map.get(&node.index()) {
                         let reader = channel.reader(|buffer| Some(&buffer_data[buffer.index()].1));
@@ -668,5 +799,85 @@ pub fn sys_gltf_decode(
                 factory.gltf_start_animation_group(root, id_group);
                 animation_index += 1;
             }
+
+            if !variant_names.is_empty() {
+                commands.entity(root).insert(GLTFMaterialVariants {
+                    scene_id,
+                    names: variant_names,
+                    meshes: variant_meshes,
+                });
+            }
+
+            for (index, list) in sub_emitters {
+                let list = list
+                    .into_iter()
+                    .filter_map(|(kind, node)| match node_map.get(&node) {
+                        Some(entity) => Some((kind, *entity)),
+                        None => {
+                            log::warn!("sub emitter node {} is not exits", node);
+                            None
+                        }
+                    })
+                    .collect::<Vec<_>>();
+                if list.is_empty() {
+                    continue;
+                }
+                if let Some(entity) = node_map.get(&index) {
+                    commands.entity(*entity).insert(ParticleSubEmitters(list));
+                }
+            }
+
+            if !particle_nodes.is_empty() {
+                commands.entity(root).insert(GLTFParticles(particle_nodes));
+            }
         });
 }
+
+/// 拖尾 / 默认四边形的材质: 配置了 material 时使用对应的 glTF 材质, 否则使用默认粒子材质
+fn gltf_particle_material(
+    factory: &mut GLTFAPI,
+    commands: &mut Commands,
+    material_map: &mut HashMap<EGLTFMaterialKey, EGLTFMaterial>,
+    materials: &Vec<pi_gltf::Material>,
+    config: &pi_gltf::json::Value,
+    textures: &Vec<pi_gltf::Texture>,
+    root_path: &Path,
+) -> GLTFMeshMaterial {
+    match config
+        .get("material")
+        .and_then(|v| v.as_u64())
+        .and_then(|i| materials.get(i as usize))
+    {
+        Some(material) => {
+            gltf_mesh_material(factory, commands, material_map, material, textures, root_path)
+        }
+        None => {
+            let idmat = commands.spawn_empty().id();
+            factory.gltf_particle_default_material(idmat)
+        }
+    }
+}
+
+/// 相同材质只创建一次, 返回 mesh 使用该材质所需的数据
+fn gltf_mesh_material(
+    factory: &mut GLTFAPI,
+    commands: &mut Commands,
+    material_map: &mut HashMap<EGLTFMaterialKey, EGLTFMaterial>,
+    material: &pi_gltf::Material,
+    textures: &Vec<pi_gltf::Texture>,
+    root_path: &Path,
+) -> GLTFMeshMaterial {
+    let state = GLTFMaterialState::new(material);
+    let key = factory.gltf_material_key(material, &state);
+    let created = match material_map.get(&key) {
+        Some(created) => created.clone(),
+        None => {
+            let idmat = commands.spawn_empty().id();
+            let created =
+                factory.gltf_create_material(idmat, material, &state, textures, root_path);
+            material_map.insert(key, created.clone());
+            created
+        }
+    };
+    GLTFMeshMaterial::new(material, state, created)
+}
@@ -1,11 +1,17 @@
+use std::path::Path;
+
 use bevy::prelude::Entity;
-use default_render::SingleIDBaseDefaultMaterial;
+use default_render::{shader::DefaultShader, SingleIDBaseDefaultMaterial};
 
 use pi_atom::Atom;
 
 use pi_engine_shell::prelude::*;
 
-use pi_node_materials::NodeMaterialBlocks;
+use pi_gltf::{json::Value, material::AlphaMode};
+use pi_node_materials::{
+    prelude::{BlockCutoff, BlockEmissiveTexture, BlockMainTexture},
+    NodeMaterialBlocks,
+};
 use pi_render::rhi::{BufferAddress, VertexFormat};
 use pi_scene_context::prelude::*;
 use pi_scene_math::{
@@ -14,6 +20,15 @@ use pi_scene_math::{
 };
 use unlit_material::shader::UnlitShader;
 
+use crate::{
+    extras::{material::GLTFExtrasMaterials, render_state::DEFAULT_DEPTH_COMPARE},
+    pbr::GLTFPbrExtensions,
+    texture::{
+        gltf_texture_param, GLTFEmbeddedImages, GLTFImageFormats, GLTFTextureTransform,
+        GLTFTextureURLs,
+    },
+};
+
 #[derive(SystemParam)]
 pub struct GLTFCommands<'w> {
     pub scenecmds: ResMut<'w, ActionListSceneCreate>,
@@ -32,6 +47,115 @@ pub struct GLTFCommands<'w> {
     pub geometrycreate: ResMut<'w, ActionListGeometryCreate>,
     pub defaultmat: Res<'w, SingleIDBaseDefaultMaterial>,
     pub nodematblocks: Res<'w, NodeMaterialBlocks>,
+    pub extras_materials: Res<'w, GLTFExtrasMaterials>,
+    pub embedded_images: Res<'w, GLTFEmbeddedImages>,
+    pub texture_urls: ResMut<'w, GLTFTextureURLs>,
+    pub image_formats: Res<'w, GLTFImageFormats>,
+    pub device: Res<'w, PiRenderDevice>,
+}
+
+/// glTF 核心材质属性 alphaMode / alphaCutoff / doubleSided 对应的渲染状态
+#[derive(Debug, Clone, Copy)]
+pub struct GLTFMaterialState {
+    pub pass: EPassTag,
+    pub blend: bool,
+    pub cutoff: Option<f32>,
+    pub cull: CullMode,
+}
+
+impl GLTFMaterialState {
+    pub fn new(material: &pi_gltf::Material) -> Self {
+        let (pass, blend, cutoff) = match material.alpha_mode() {
+            AlphaMode::Opaque => (EPassTag::Opaque, false, None),
+            AlphaMode::Mask => (
+                EPassTag::Opaque,
+                false,
+                Some(material.alpha_cutoff().unwrap_or(0.5)),
+            ),
+            AlphaMode::Blend => (EPassTag::Transparent, true, None),
+        };
+
+        let cull = if material.double_sided() {
+            CullMode::Off
+        } else {
+            CullMode::Back
+        };
+
+        Self {
+            pass,
+            blend,
+            cutoff,
+            cull,
+        }
+    }
+}
+
+/// 材质缓存的 key
+///   * 相同 glTF 材质索引的 primitive 共用一个材质
+///   * extras 材质按配置内容区分, 不同索引但配置相同的材质也会共用
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum EGLTFMaterialKey {
+    Index(Option<usize>),
+    /// (extras 配置, alphaCutoff)
+    Extras(String, Option<u32>),
+}
+
+/// 已创建的材质, 每个使用它的 mesh 还需通过 gltf_apply_material 设置渲染状态
+#[derive(Debug, Clone)]
+pub enum EGLTFMaterial {
+    Default,
+    /// 带有核心自发光或 MASK / BLEND 的默认材质, 不能与其他 mesh 共用 SingleIDBaseDefaultMaterial
+    DefaultEmissive(Entity),
+    Unlit(Entity),
+    /// (材质, 匹配到的 extras key)
+    Extras(Entity, String),
+}
+
+/// mesh 使用某个材质所需的全部数据, 加载完成后仍可重新应用 (KHR_materials_variants)
+#[derive(Debug, Clone)]
+pub struct GLTFMeshMaterial {
+    pub state: GLTFMaterialState,
+    pub created: EGLTFMaterial,
+    /// extras 材质的配置 (extras[key]), 其中的渲染状态覆盖 glTF 核心属性
+    pub extras: Option<Value>,
+}
+
+impl GLTFMeshMaterial {
+    pub fn new(
+        material: &pi_gltf::Material,
+        state: GLTFMaterialState,
+        created: EGLTFMaterial,
+    ) -> Self {
+        let mut extras = None;
+        if let (EGLTFMaterial::Extras(_, key), Some(info)) = (&created, material.extras()) {
+            extras = info.get(key).cloned();
+        }
+        Self {
+            state,
+            created,
+            extras,
+        }
+    }
+}
+
+/// 材质 extensions 中的 PBR 扩展, 解析错误输出提示
+/// 现有材质都不支持这些扩展, 创建材质时只提示被忽略
+pub fn gltf_pbr_extensions(material: &pi_gltf::Material) -> GLTFPbrExtensions {
+    let extensions = match material.extensions() {
+        Some(extensions) => Value::Object(extensions.clone()),
+        None => return GLTFPbrExtensions::default(),
+    };
+
+    let mut errors = vec![];
+    let result = GLTFPbrExtensions::from_json(&extensions, &mut errors);
+    for err in errors {
+        log::warn!("material {:?}.{}", material.name(), err);
+    }
+    result
+}
+
+pub fn gltf_has_emissive(material: &pi_gltf::Material) -> bool {
+    material.emissive_factor() != [0., 0., 0.] || material.emissive_texture().is_some()
 }
 
 pub struct GLTFAPI<'a, 'b> {
@@ -215,7 +339,7 @@ impl<'a, 'b> GLTFAPI<'a, 'b> {
         ));
     }
 
-    pub fn gltf_default_material(&mut self, entity: ObjectID, idmat: Entity) {
+    pub fn gltf_default_material(&mut self, entity: ObjectID) {
         // self.commands.matcmds.create.push(OpsMaterialCreate::ops(
         //     idmat,
         //     DefaultShader::KEY,
@@ -235,15 +359,283 @@ impl<'a, 'b> GLTFAPI<'a, 'b> {
             .push(OpsMaterialUse::ops(entity, materialid));
     }
 
-    pub fn gltf_create_unlit_material(&mut self, idmat: Entity) -> ObjectID {
-        self.commands.matcmds.create.push(OpsMaterialCreate::ops(
+    pub fn gltf_create_unlit_material(&mut self, idmat: Entity, pass: EPassTag) -> ObjectID {
+        self.commands
+            .matcmds
+            .create
+            .push(OpsMaterialCreate::ops(idmat, UnlitShader::KEY, pass));
+        idmat
+    }
+
+    /// 网格的 cull / blend, extras 材质在之后设置的值会覆盖这里
+    pub fn gltf_mesh_render_state(&mut self, entity: Entity, state: &GLTFMaterialState) {
+        self.commands
+            .meshcmds
+            .cullmode
+            .push(OpsCullMode::ops(entity, state.cull));
+
+        if state.blend {
+            let mut blend = ModelBlend::default();
+            blend.combine();
+            self.commands
+                .meshcmds
+                .blend
+                .push(OpsRenderBlend::ops(entity, blend));
+            self.commands
+                .meshcmds
+                .render_queue
+                .push(OpsRenderQueue::ops(entity, 0, 3000));
+        }
+    }
+
+    /// 不透明物体的渲染状态, 切换材质前使用
+    /// 需包含 extras 材质可设置的所有状态 (cull / zWrite / zTest / alphaMode / renderQueue)
+    pub fn gltf_reset_render_state(&mut self, entity: Entity) {
+        self.commands
+            .meshcmds
+            .cullmode
+            .push(OpsCullMode::ops(entity, CullMode::Back));
+        self.commands
+            .meshcmds
+            .blend
+            .push(OpsRenderBlend::ops(entity, ModelBlend::default()));
+        self.commands
+            .meshcmds
+            .depth_write
+            .push(OpsDepthWrite::ops(entity, true));
+        self.commands
+            .meshcmds
+            .depth_compare
+            .push(OpsDepthCompare::ops(entity, DEFAULT_DEPTH_COMPARE));
+        self.commands
+            .meshcmds
+            .render_queue
+            .push(OpsRenderQueue::ops(entity, 0, 2000));
+    }
+
+    pub fn gltf_material_cutoff(&mut self, idmat: Entity, state: &GLTFMaterialState) {
+        if let Some(cutoff) = state.cutoff {
+            self.commands.matcmds.float.push(OpsUniformFloat::ops(
+                idmat,
+                Atom::from(BlockCutoff::KEY_VALUE),
+                cutoff,
+            ));
+        }
+    }
+
+    /// KHR_texture_transform 写入纹理块的 tilloff
+    /// 纹理块不支持的 rotation / texCoord 返回错误, scale / offset 仍会写入
+    pub fn gltf_texture_transform(
+        &mut self,
+        idmat: Entity,
+        key_tilloff: &str,
+        transform: &GLTFTextureTransform,
+    ) -> Result<(), String> {
+        let tilloff = transform.tilloff();
+        self.commands.matcmds.vec4.push(OpsUniformVec4::ops(
             idmat,
-            UnlitShader::KEY,
-            EPassTag::Opaque,
+            Atom::from(key_tilloff),
+            tilloff[0],
+            tilloff[1],
+            tilloff[2],
+            tilloff[3],
         ));
+
+        transform.check()
+    }
+
+    /// KHR_materials_unlit: 使用 UnlitShader, 取 baseColorFactor 与 baseColorTexture
+    pub fn gltf_unlit_material(
+        &mut self,
+        idmat: Entity,
+        material: &pi_gltf::Material,
+        state: &GLTFMaterialState,
+        root_path: &Path,
+    ) -> ObjectID {
+        self.gltf_create_unlit_material(idmat, state.pass);
+        self.gltf_material_cutoff(idmat, state);
+
+        let pbr = material.pbr_metallic_roughness();
+        let base_color = pbr.base_color_factor();
+        self.commands.matcmds.vec4.push(OpsUniformVec4::ops(
+            idmat,
+            Atom::from(BlockMainTexture::KEY_COLOR),
+            base_color[0],
+            base_color[1],
+            base_color[2],
+            base_color[3],
+        ));
+
+        if let Some(info) = pbr.base_color_texture() {
+            self.commands.matcmds.texture.push(OpsUniformTexture::ops(
+                idmat,
+                gltf_texture_param(
+                    &self.commands.texture_urls,
+                    root_path,
+                    BlockMainTexture::KEY_TEX,
+                    &info.texture(),
+                ),
+            ));
+            if let Some(transform) = GLTFTextureTransform::from_info(&info) {
+                if let Err(err) =
+                    self.gltf_texture_transform(idmat, BlockMainTexture::KEY_TILLOFF, &transform)
+                {
+                    log::warn!("material {:?}.baseColorTexture.{}", material.name(), err);
+                }
+            }
+        }
+
+        // UnlitShader 没有自发光块
+        if gltf_has_emissive(material) {
+            log::warn!(
+                "material {:?}.emissive: is not supported by the unlit material",
+                material.name()
+            );
+        }
+
         idmat
     }
 
+    /// 有核心自发光或非 OPAQUE 时创建默认材质的实例, 按 state.pass 创建并写入 cutoff
+    /// 有自发光时写入 emissiveFactor / emissiveTexture
+    pub fn gltf_default_emissive_material(
+        &mut self,
+        idmat: Entity,
+        material: &pi_gltf::Material,
+        state: &GLTFMaterialState,
+        root_path: &Path,
+    ) -> ObjectID {
+        self.commands
+            .matcmds
+            .create
+            .push(OpsMaterialCreate::ops(idmat, DefaultShader::KEY, state.pass));
+        self.gltf_material_cutoff(idmat, state);
+        self.gltf_material_emissive(idmat, material, root_path);
+        idmat
+    }
+
+    /// emissiveFactor / emissiveTexture
+    /// 颜色乘以 KHR_materials_emissive_strength 后写入, 可大于 1, 供 bloom 使用
+    /// 没有自发光时不设置, 保留材质自身的默认值
+    pub fn gltf_material_emissive(
+        &mut self,
+        idmat: Entity,
+        material: &pi_gltf::Material,
+        root_path: &Path,
+    ) -> bool {
+        if !gltf_has_emissive(material) {
+            return false;
+        }
+
+        let factor = material.emissive_factor();
+        let strength = material.emissive_strength().unwrap_or(1.);
+        self.commands.matcmds.vec4.push(OpsUniformVec4::ops(
+            idmat,
+            Atom::from(BlockEmissiveTexture::KEY_INFO),
+            factor[0] * strength,
+            factor[1] * strength,
+            factor[2] * strength,
+            1.,
+        ));
+
+        if let Some(info) = material.emissive_texture() {
+            self.commands.matcmds.texture.push(OpsUniformTexture::ops(
+                idmat,
+                gltf_texture_param(
+                    &self.commands.texture_urls,
+                    root_path,
+                    BlockEmissiveTexture::KEY_TEX,
+                    &info.texture(),
+                ),
+            ));
+            if let Some(transform) = GLTFTextureTransform::from_info(&info) {
+                if let Err(err) = self.gltf_texture_transform(
+                    idmat,
+                    BlockEmissiveTexture::KEY_TILLOFF,
+                    &transform,
+                ) {
+                    log::warn!("material {:?}.emissiveTexture.{}", material.name(), err);
+                }
+            }
+        }
+
+        true
+    }
+
+    pub fn gltf_material_key(
+        &self,
+        material: &pi_gltf::Material,
+        state: &GLTFMaterialState,
+    ) -> EGLTFMaterialKey {
+        // 核心自发光不在 extras 中, 此时只按索引共用
+        if let (Some(extras), false) = (material.extras(), gltf_has_emissive(material)) {
+            if self.commands.extras_materials.find(extras).is_some() {
+                return EGLTFMaterialKey::Extras(
+                    extras.to_string(),
+                    state.cutoff.map(|v| v.to_bits()),
+                );
+            }
+        }
+        EGLTFMaterialKey::Index(material.index())
+    }
+
+    /// 创建材质: extras 材质优先, 其次 KHR_materials_unlit, 否则使用默认材质
+    pub fn gltf_create_material(
+        &mut self,
+        idmat: Entity,
+        material: &pi_gltf::Material,
+        state: &GLTFMaterialState,
+        textures: &Vec<pi_gltf::Texture>,
+        root_path: &Path,
+    ) -> EGLTFMaterial {
+        let pbr = gltf_pbr_extensions(material);
+        if !pbr.properties().is_empty() {
+            log::warn!(
+                "material {:?} pbr extensions {:?} are not supported, ignored",
+                material.name(),
+                pbr.properties()
+            );
+        }
+
+        if let Some(extras) = material.extras() {
+            if let Some(key) =
+                self.gltf_extras_material(idmat, material, state, extras, textures, root_path)
+            {
+                return EGLTFMaterial::Extras(idmat, key);
+            }
+        }
+
+        if material.unlit() {
+            self.gltf_unlit_material(idmat, material, state, root_path);
+            EGLTFMaterial::Unlit(idmat)
+        } else if gltf_has_emissive(material) || state.blend || state.cutoff.is_some() {
+            self.gltf_default_emissive_material(idmat, material, state, root_path);
+            EGLTFMaterial::DefaultEmissive(idmat)
+        } else {
+            EGLTFMaterial::Default
+        }
+    }
+
+    /// 设置 mesh 的渲染状态并使用已创建的材质
+    pub fn gltf_apply_material(&mut self, entity: Entity, mesh_material: &GLTFMeshMaterial) {
+        let state = &mesh_material.state;
+        self.gltf_mesh_render_state(entity, state);
+        match &mesh_material.created {
+            EGLTFMaterial::Default => {
+                self.gltf_default_material(entity);
+            }
+            EGLTFMaterial::DefaultEmissive(idmat) | EGLTFMaterial::Unlit(idmat) => {
+                self.gltf_use_material(entity, *idmat);
+            }
+            EGLTFMaterial::Extras(idmat, key) => {
+                if let Some(info) = &mesh_material.extras {
+                    self.gltf_extras_render_state(entity, key, info);
+                }
+                self.gltf_use_material(entity, *idmat);
+            }
+        }
+    }
+
     pub fn gltf_create_skin(
         &mut self,
         bone_root: ObjectID,
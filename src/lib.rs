@@ -2,5 +2,73 @@ pub mod factory;
 pub mod interface;
 pub mod extras;
 pub mod animation;
+pub mod texture;
+pub mod pbr;
+pub mod variants;
 
-pub mod test;
\ No newline at end of file
+pub mod test;
+#[cfg(test)]
+mod test_util;
+
+use bevy::prelude::{App, IntoSystemConfig, Plugin};
+use pi_engine_shell::prelude::*;
+
+use crate::{
+    extras::{
+        material::GLTFExtrasMaterials,
+        particle_control::{
+            sys_gltf_particle_control, sys_gltf_particle_playback, ActionListGLTFParticleControl,
+        },
+    },
+    factory::{sys_gltf_decode, sys_gltf_release, ActionListGLTFLoaded, ActionListGLTFRelease},
+    texture::{GLTFEmbeddedImages, GLTFImageFormats, GLTFTextureURLs},
+    variants::{sys_gltf_select_variant, ActionListGLTFSelectVariant},
+};
+
+/// 注册加载 gltf 所需的资源与系统
+///   * 已存在的资源不会被替换, 可在之前插入自定义的 GLTFExtrasMaterials / GLTFImageFormats
+///   * 加载回调使用的 GLTFEmbeddedImages 需与资源中的是同一份 (clone)
+pub struct PluginGLTFLoader;
+impl Plugin for PluginGLTFLoader {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ActionListGLTFLoaded>();
+        app.init_resource::<GLTFExtrasMaterials>();
+        app.init_resource::<GLTFEmbeddedImages>();
+        app.init_resource::<GLTFTextureURLs>();
+        app.init_resource::<GLTFImageFormats>();
+        app.init_resource::<ActionListGLTFSelectVariant>();
+        app.init_resource::<ActionListGLTFRelease>();
+
+        app.add_system(sys_gltf_decode.in_set(ERunStageChap::Command));
+        app.add_system(
+            sys_gltf_select_variant
+                .after(sys_gltf_decode)
+                .in_set(ERunStageChap::Command),
+        );
+        app.add_system(
+            sys_gltf_release
+                .after(sys_gltf_decode)
+                .in_set(ERunStageChap::Command),
+        );
+    }
+}
+
+/// 注册粒子播放控制, 需在 PluginGLTFLoader 之后添加
+///   * 播放计时使用 bevy 的 Time 资源
+pub struct PluginGLTFParticle;
+impl Plugin for PluginGLTFParticle {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ActionListGLTFParticleControl>();
+
+        app.add_system(
+            sys_gltf_particle_control
+                .after(sys_gltf_decode)
+                .in_set(ERunStageChap::Command),
+        );
+        app.add_system(
+            sys_gltf_particle_playback
+                .after(sys_gltf_particle_control)
+                .in_set(ERunStageChap::Command),
+        );
+    }
+}
@@ -0,0 +1,361 @@
+/// # PBR 扩展
+///   * KHR_materials_clearcoat / sheen / transmission / ior / specular / volume 解析为 GLTFPbrExtensions
+///   * 只解析不写入材质: 目前没有支持这些属性的材质, 加载时输出被忽略的扩展
+///   * 数值按 ExtrasUniform 的分量规则读取 (read_components)
+///   * 纹理参数按 textureInfo 解析, 包括 KHR_texture_transform
+use pi_gltf::json::Value;
+
+use crate::{extras::uniform::read_components, texture::GLTFTextureTransform};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EGLTFPbrProperty {
+    Clearcoat,
+    Sheen,
+    Transmission,
+    Ior,
+    Specular,
+    Volume,
+}
+
+impl EGLTFPbrProperty {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            EGLTFPbrProperty::Clearcoat => "KHR_materials_clearcoat",
+            EGLTFPbrProperty::Sheen => "KHR_materials_sheen",
+            EGLTFPbrProperty::Transmission => "KHR_materials_transmission",
+            EGLTFPbrProperty::Ior => "KHR_materials_ior",
+            EGLTFPbrProperty::Specular => "KHR_materials_specular",
+            EGLTFPbrProperty::Volume => "KHR_materials_volume",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GLTFTextureRef {
+    pub index: usize,
+    pub transform: Option<GLTFTextureTransform>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GLTFClearcoat {
+    pub factor: f32,
+    pub texture: Option<GLTFTextureRef>,
+    pub roughness_factor: f32,
+    pub roughness_texture: Option<GLTFTextureRef>,
+    pub normal_texture: Option<GLTFTextureRef>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GLTFSheen {
+    pub color_factor: [f32; 3],
+    pub color_texture: Option<GLTFTextureRef>,
+    pub roughness_factor: f32,
+    pub roughness_texture: Option<GLTFTextureRef>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GLTFTransmission {
+    pub factor: f32,
+    pub texture: Option<GLTFTextureRef>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GLTFSpecular {
+    pub factor: f32,
+    pub texture: Option<GLTFTextureRef>,
+    pub color_factor: [f32; 3],
+    pub color_texture: Option<GLTFTextureRef>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GLTFVolume {
+    pub thickness_factor: f32,
+    pub thickness_texture: Option<GLTFTextureRef>,
+    /// 未配置时为 f32::INFINITY
+    pub attenuation_distance: f32,
+    pub attenuation_color: [f32; 3],
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct GLTFPbrExtensions {
+    pub clearcoat: Option<GLTFClearcoat>,
+    pub sheen: Option<GLTFSheen>,
+    pub transmission: Option<GLTFTransmission>,
+    pub ior: Option<f32>,
+    pub specular: Option<GLTFSpecular>,
+    pub volume: Option<GLTFVolume>,
+}
+
+/// 按 ExtrasUniform 的分量规则读取, 未配置或错误时使用 default
+fn read_factor<const N: usize>(
+    info: &Value,
+    key: &str,
+    default: [f32; N],
+    errors: &mut Vec<String>,
+) -> [f32; N] {
+    let value = match info.get(key) {
+        Some(value) => value,
+        None => return default,
+    };
+    match read_components(value, N) {
+        Ok(values) => {
+            let mut result = default;
+            result.copy_from_slice(&values);
+            result
+        }
+        Err(err) => {
+            errors.push(format!("{}: {}", key, err));
+            default
+        }
+    }
+}
+
+fn read_f32(info: &Value, key: &str, default: f32, errors: &mut Vec<String>) -> f32 {
+    read_factor(info, key, [default], errors)[0]
+}
+
+fn read_texture(info: &Value, key: &str, errors: &mut Vec<String>) -> Option<GLTFTextureRef> {
+    let texture = info.get(key)?;
+    match texture.get("index").and_then(|v| v.as_u64()) {
+        Some(index) => {
+            let mut transform_errors = vec![];
+            let transform = GLTFTextureTransform::from_json(texture, &mut transform_errors);
+            errors.extend(transform_errors.into_iter().map(|err| format!("{}.{}", key, err)));
+            Some(GLTFTextureRef {
+                index: index as usize,
+                transform,
+            })
+        }
+        None => {
+            errors.push(format!("{}: missing texture index", key));
+            None
+        }
+    }
+}
+
+impl GLTFPbrExtensions {
+    /// extensions 为材质的 extensions 对象, 错误的字段记录到 errors 并使用规范默认值
+    pub fn from_json(extensions: &Value, errors: &mut Vec<String>) -> Self {
+        let mut result = Self::default();
+
+        if let Some(info) = extensions.get(EGLTFPbrProperty::Clearcoat.extension()) {
+            result.clearcoat = Some(GLTFClearcoat {
+                factor: read_f32(info, "clearcoatFactor", 0., errors),
+                texture: read_texture(info, "clearcoatTexture", errors),
+                roughness_factor: read_f32(info, "clearcoatRoughnessFactor", 0., errors),
+                roughness_texture: read_texture(info, "clearcoatRoughnessTexture", errors),
+                normal_texture: read_texture(info, "clearcoatNormalTexture", errors),
+            });
+        }
+
+        if let Some(info) = extensions.get(EGLTFPbrProperty::Sheen.extension()) {
+            result.sheen = Some(GLTFSheen {
+                color_factor: read_factor(info, "sheenColorFactor", [0., 0., 0.], errors),
+                color_texture: read_texture(info, "sheenColorTexture", errors),
+                roughness_factor: read_f32(info, "sheenRoughnessFactor", 0., errors),
+                roughness_texture: read_texture(info, "sheenRoughnessTexture", errors),
+            });
+        }
+
+        if let Some(info) = extensions.get(EGLTFPbrProperty::Transmission.extension()) {
+            result.transmission = Some(GLTFTransmission {
+                factor: read_f32(info, "transmissionFactor", 0., errors),
+                texture: read_texture(info, "transmissionTexture", errors),
+            });
+        }
+
+        if let Some(info) = extensions.get(EGLTFPbrProperty::Ior.extension()) {
+            result.ior = Some(read_f32(info, "ior", 1.5, errors));
+        }
+
+        if let Some(info) = extensions.get(EGLTFPbrProperty::Specular.extension()) {
+            result.specular = Some(GLTFSpecular {
+                factor: read_f32(info, "specularFactor", 1., errors),
+                texture: read_texture(info, "specularTexture", errors),
+                color_factor: read_factor(info, "specularColorFactor", [1., 1., 1.], errors),
+                color_texture: read_texture(info, "specularColorTexture", errors),
+            });
+        }
+
+        if let Some(info) = extensions.get(EGLTFPbrProperty::Volume.extension()) {
+            result.volume = Some(GLTFVolume {
+                thickness_factor: read_f32(info, "thicknessFactor", 0., errors),
+                thickness_texture: read_texture(info, "thicknessTexture", errors),
+                attenuation_distance: read_f32(
+                    info,
+                    "attenuationDistance",
+                    f32::INFINITY,
+                    errors,
+                ),
+                attenuation_color: read_factor(info, "attenuationColor", [1., 1., 1.], errors),
+            });
+        }
+
+        result
+    }
+
+    pub fn properties(&self) -> Vec<EGLTFPbrProperty> {
+        let mut result = vec![];
+        if self.clearcoat.is_some() {
+            result.push(EGLTFPbrProperty::Clearcoat);
+        }
+        if self.sheen.is_some() {
+            result.push(EGLTFPbrProperty::Sheen);
+        }
+        if self.transmission.is_some() {
+            result.push(EGLTFPbrProperty::Transmission);
+        }
+        if self.ior.is_some() {
+            result.push(EGLTFPbrProperty::Ior);
+        }
+        if self.specular.is_some() {
+            result.push(EGLTFPbrProperty::Specular);
+        }
+        if self.volume.is_some() {
+            result.push(EGLTFPbrProperty::Volume);
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_util;
+
+    fn parse(text: &str) -> (GLTFPbrExtensions, Vec<String>) {
+        test_util::parse(text, GLTFPbrExtensions::from_json)
+    }
+
+    #[test]
+    fn test_pbr_none() {
+        let (pbr, errors) = parse(r#"{ "KHR_materials_unlit": {} }"#);
+        assert_eq!(pbr, GLTFPbrExtensions::default());
+        assert!(pbr.properties().is_empty());
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn test_pbr_defaults() {
+        let (pbr, errors) = parse(
+            r#"{
+                "KHR_materials_clearcoat": {},
+                "KHR_materials_sheen": {},
+                "KHR_materials_transmission": {},
+                "KHR_materials_ior": {},
+                "KHR_materials_specular": {},
+                "KHR_materials_volume": {}
+            }"#,
+        );
+        assert!(errors.is_empty());
+        assert_eq!(
+            pbr.properties(),
+            vec![
+                EGLTFPbrProperty::Clearcoat,
+                EGLTFPbrProperty::Sheen,
+                EGLTFPbrProperty::Transmission,
+                EGLTFPbrProperty::Ior,
+                EGLTFPbrProperty::Specular,
+                EGLTFPbrProperty::Volume,
+            ]
+        );
+        assert_eq!(pbr.clearcoat.unwrap().factor, 0.);
+        assert_eq!(pbr.sheen.unwrap().color_factor, [0., 0., 0.]);
+        assert_eq!(pbr.ior, Some(1.5));
+        let specular = pbr.specular.unwrap();
+        assert_eq!(specular.factor, 1.);
+        assert_eq!(specular.color_factor, [1., 1., 1.]);
+        let volume = pbr.volume.unwrap();
+        assert_eq!(volume.attenuation_distance, f32::INFINITY);
+        assert_eq!(volume.attenuation_color, [1., 1., 1.]);
+    }
+
+    #[test]
+    fn test_pbr_values() {
+        let (pbr, errors) = parse(
+            r#"{
+                "KHR_materials_clearcoat": {
+                    "clearcoatFactor": 0.5,
+                    "clearcoatRoughnessFactor": 0.25,
+                    "clearcoatNormalTexture": {
+                        "index": 2,
+                        "extensions": {
+                            "KHR_texture_transform": { "offset": [0.5, 0], "scale": [2, 2] }
+                        }
+                    }
+                },
+                "KHR_materials_volume": { "thicknessFactor": 1, "attenuationDistance": 3 }
+            }"#,
+        );
+        assert!(errors.is_empty());
+        assert_eq!(
+            pbr.clearcoat,
+            Some(GLTFClearcoat {
+                factor: 0.5,
+                texture: None,
+                roughness_factor: 0.25,
+                roughness_texture: None,
+                normal_texture: Some(GLTFTextureRef {
+                    index: 2,
+                    transform: Some(GLTFTextureTransform {
+                        offset: [0.5, 0.],
+                        scale: [2., 2.],
+                        ..Default::default()
+                    }),
+                }),
+            })
+        );
+        let volume = pbr.volume.unwrap();
+        assert_eq!(volume.thickness_factor, 1.);
+        assert_eq!(volume.attenuation_distance, 3.);
+    }
+
+    #[test]
+    fn test_pbr_errors() {
+        let (pbr, errors) = parse(
+            r#"{
+                "KHR_materials_sheen": { "sheenColorFactor": [1, 1], "sheenRoughnessFactor": "a" },
+                "KHR_materials_transmission": { "transmissionTexture": { "texCoord": 0 } }
+            }"#,
+        );
+        assert_eq!(errors.len(), 3);
+        assert!(errors[0].starts_with("sheenColorFactor:"));
+        assert!(errors[1].starts_with("sheenRoughnessFactor:"));
+        assert!(errors[2].starts_with("transmissionTexture:"));
+        // 错误的字段使用规范默认值
+        let sheen = pbr.sheen.unwrap();
+        assert_eq!(sheen.color_factor, [0., 0., 0.]);
+        assert_eq!(sheen.roughness_factor, 0.);
+        assert_eq!(pbr.transmission.unwrap().texture, None);
+    }
+
+    #[test]
+    fn test_pbr_texture_transform_errors() {
+        let (pbr, errors) = parse(
+            r#"{
+                "KHR_materials_clearcoat": {
+                    "clearcoatTexture": {
+                        "index": 1,
+                        "extensions": {
+                            "KHR_texture_transform": { "offset": [0.5], "rotation": "a", "scale": [2, 2] }
+                        }
+                    }
+                }
+            }"#,
+        );
+        assert_eq!(errors.len(), 2);
+        assert!(errors[0].starts_with("clearcoatTexture.KHR_texture_transform.offset:"));
+        assert!(errors[1].starts_with("clearcoatTexture.KHR_texture_transform.rotation:"));
+        // 错误的字段不做变换, 其它字段照常读取
+        assert_eq!(
+            pbr.clearcoat.unwrap().texture,
+            Some(GLTFTextureRef {
+                index: 1,
+                transform: Some(GLTFTextureTransform {
+                    scale: [2., 2.],
+                    ..Default::default()
+                }),
+            })
+        );
+    }
+}
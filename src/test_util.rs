@@ -0,0 +1,16 @@
+/// # 单元测试共用的辅助函数
+use pi_gltf::json::{deserialize::from_str, Value};
+
+pub fn json(text: &str) -> Value {
+    from_str(text).unwrap()
+}
+
+/// 用 from_json 解析 json 文本, 返回结果与记录的错误
+pub fn parse<T>(
+    text: &str,
+    from_json: impl FnOnce(&Value, &mut Vec<String>) -> T,
+) -> (T, Vec<String>) {
+    let mut errors = vec![];
+    let result = from_json(&json(text), &mut errors);
+    (result, errors)
+}
@@ -0,0 +1,478 @@
+/// # glTF 纹理
+///   * 图片来源: uri 文件 / data uri / bufferView
+///   * 内嵌图片 (data uri / bufferView) 解码后按 "gltf路径#image索引" 登记在 GLTFEmbeddedImages 中
+///   * 加载回调 (pi_hal::init_load_cb) 中应先通过 GLTFEmbeddedImages::get 取得内嵌图片
+///   * 内嵌图片保留到 gltf 释放 (OpsGLTFRelease), 纹理被回收后可再次加载
+/// # 图片格式扩展
+///   * 运行时支持的格式由 GLTFImageFormats 资源设置, 按 KTX2 > AVIF > WebP 的顺序选用扩展中的图片, 都不支持时使用 source 回退
+///   * KHR_texture_basisu / EXT_texture_avif / EXT_texture_webp
+///   * KTX2 的转码目标在加载 gltf 时按渲染设备的 features 选择 (EKTX2Target::from_features)
+///   * KTX2 纹理 key 为 "图片key#转码目标", 如 "a/b.ktx2#bc7", 加载回调通过 split_ktx2_target 取出
+///   * 每个 texture 选用的图片在加载 gltf 时登记到 GLTFTextureURLs, key 为 "gltf路径#texture索引"
+/// # 采样器
+///   * 由 glTF sampler 的 wrapS/wrapT, magFilter/minFilter 构建 KeySampler
+///   * 未指定 sampler 时按规范使用 repeat, 过滤默认 linear
+/// # KHR_texture_transform
+///   * scale / offset 映射到各纹理块的 tilloff: uv * tilloff.xy + tilloff.zw
+///   * 现有纹理块没有旋转与 uv 通道参数, 非 0 的 rotation / texCoord 作为不支持的配置返回错误, 由调用方按材质报告
+use std::{
+    collections::BTreeMap,
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use bevy::prelude::Resource;
+use pi_atom::Atom;
+use pi_engine_shell::prelude::*;
+use pi_gltf::{
+    image,
+    json::Value,
+    texture::{self, MagFilter, MinFilter, WrappingMode},
+    Gltf,
+};
+use pi_render::{
+    renderer::sampler::{EAddressMode, EFilterMode},
+    rhi::Features,
+};
+
+pub const KHR_TEXTURE_BASISU: &'static str = "KHR_texture_basisu";
+pub const EXT_TEXTURE_AVIF: &'static str = "EXT_texture_avif";
+pub const EXT_TEXTURE_WEBP: &'static str = "EXT_texture_webp";
+
+/// KTX2 (Basis Universal) 的转码目标
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EKTX2Target {
+    BC7,
+    ASTC4x4,
+    ETC2,
+    RGBA8,
+}
+
+impl EKTX2Target {
+    /// 按设备支持的压缩格式选择, 都不支持时解码为 rgba8
+    pub fn from_features(features: Features) -> Self {
+        if features.contains(Features::TEXTURE_COMPRESSION_BC) {
+            Self::BC7
+        } else if features.contains(Features::TEXTURE_COMPRESSION_ASTC_LDR) {
+            Self::ASTC4x4
+        } else if features.contains(Features::TEXTURE_COMPRESSION_ETC2) {
+            Self::ETC2
+        } else {
+            Self::RGBA8
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            EKTX2Target::BC7 => "bc7",
+            EKTX2Target::ASTC4x4 => "astc4x4",
+            EKTX2Target::ETC2 => "etc2",
+            EKTX2Target::RGBA8 => "rgba8",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "bc7" => Some(Self::BC7),
+            "astc4x4" => Some(Self::ASTC4x4),
+            "etc2" => Some(Self::ETC2),
+            "rgba8" => Some(Self::RGBA8),
+            _ => None,
+        }
+    }
+}
+
+/// 运行时图片加载器支持的格式, 需在加载 gltf 之前设置
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Resource)]
+pub struct GLTFImageFormats {
+    /// 加载器能否转码 KTX2, 转码目标由渲染设备决定
+    pub ktx2: bool,
+    pub avif: bool,
+    pub webp: bool,
+}
+
+impl GLTFImageFormats {
+    pub const fn new() -> Self {
+        Self {
+            ktx2: false,
+            avif: false,
+            webp: false,
+        }
+    }
+}
+
+impl GLTFImageFormats {
+    /// None: 不支持 KTX2
+    pub fn ktx2_target(&self, features: Features) -> Option<EKTX2Target> {
+        if self.ktx2 {
+            Some(EKTX2Target::from_features(features))
+        } else {
+            None
+        }
+    }
+}
+
+impl Default for GLTFImageFormats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 拆分纹理 key 中的 KTX2 转码目标: "a/b.ktx2#bc7" -> ("a/b.ktx2", Some(BC7))
+pub fn split_ktx2_target(key: &str) -> (&str, Option<EKTX2Target>) {
+    if let Some(index) = key.rfind('#') {
+        if let Some(target) = EKTX2Target::from_name(&key[index + 1..]) {
+            return (&key[..index], Some(target));
+        }
+    }
+    (key, None)
+}
+
+pub fn embedded_image_key(root_path: &Path, image_index: usize) -> String {
+    format!("{}#image{}", root_path.to_str().unwrap(), image_index)
+}
+
+/// 内嵌图片数据
+///   * 加载回调在其他线程执行, 可 clone 一份在回调中使用, 与资源共享同一张表
+///   * gltf 释放时通过 release 清除该 gltf 的所有内嵌图片
+#[derive(Debug, Clone, Default, Resource)]
+pub struct GLTFEmbeddedImages(Arc<Mutex<BTreeMap<String, Vec<u8>>>>);
+
+impl GLTFEmbeddedImages {
+    pub fn insert(&self, key: String, data: Vec<u8>) {
+        self.0.lock().unwrap().insert(key, data);
+    }
+
+    pub fn get(&self, key: &str) -> Option<Vec<u8>> {
+        self.0.lock().unwrap().get(key).cloned()
+    }
+
+    /// 清除 root_path 对应 gltf 的所有内嵌图片
+    pub fn release(&self, root_path: &Path) {
+        let prefix = format!("{}#image", root_path.to_str().unwrap());
+        self.0.lock().unwrap().retain(|key, _| !key.starts_with(&prefix));
+    }
+}
+
+/// 正在加载的 gltf 中每个 texture 选用的图片 url
+#[derive(Debug, Default, Resource)]
+pub struct GLTFTextureURLs(BTreeMap<String, String>);
+
+fn is_data_uri(uri: &str) -> bool {
+    uri.starts_with("data:")
+}
+
+fn decode_data_uri(uri: &str) -> Option<Vec<u8>> {
+    let index = uri.find(',')?;
+    let base64_buffer = uri.split_at(index + 1).1;
+    base64::decode(base64_buffer).ok()
+}
+
+/// 登记 gltf 中所有内嵌图片
+pub fn gltf_regist_embedded_images(
+    images: &GLTFEmbeddedImages,
+    gltf: &Gltf,
+    root_path: &Path,
+    buffer_data: &Vec<(String, Vec<u8>)>,
+) {
+    for img in gltf.images() {
+        let key = embedded_image_key(root_path, img.index());
+        match img.source() {
+            image::Source::View { view, mime_type: _ } => {
+                match buffer_view_data(&view, buffer_data) {
+                    Ok(data) => images.insert(key, data.to_vec()),
+                    Err(err) => log::warn!("image {}: {}", img.index(), err),
+                }
+            }
+            image::Source::Uri { uri, mime_type: _ } => {
+                if is_data_uri(uri) {
+                    match decode_data_uri(uri) {
+                        Some(data) => images.insert(key, data),
+                        None => log::warn!("image {} data uri decode failed!!", img.index()),
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// bufferView 在 buffer 中的数据, 超出 buffer 范围时返回错误
+fn buffer_view_data<'a>(
+    view: &pi_gltf::buffer::View,
+    buffer_data: &'a [(String, Vec<u8>)],
+) -> Result<&'a [u8], String> {
+    let index = view.buffer().index();
+    let buffer = match buffer_data.get(index) {
+        Some((_, buffer)) => buffer,
+        None => return Err(format!("buffer {} is not exits", index)),
+    };
+    let start = view.offset();
+    start
+        .checked_add(view.length())
+        .and_then(|end| buffer.get(start..end))
+        .ok_or_else(|| {
+            format!(
+                "bufferView {} (offset {}, length {}) is out of buffer {} (length {})",
+                view.index(),
+                start,
+                view.length(),
+                index,
+                buffer.len()
+            )
+        })
+}
+
+fn texture_key(root_path: &Path, texture_index: usize) -> String {
+    format!("{}#texture{}", root_path.to_str().unwrap(), texture_index)
+}
+
+fn image_url(root_path: &Path, img: &pi_gltf::Image) -> String {
+    match img.source() {
+        image::Source::View {
+            view: _,
+            mime_type: _,
+        } => embedded_image_key(root_path, img.index()),
+        image::Source::Uri { uri, mime_type: _ } => {
+            if is_data_uri(uri) {
+                embedded_image_key(root_path, img.index())
+            } else {
+                let path = root_path.parent().unwrap().join(uri);
+                String::from(path.to_str().unwrap())
+            }
+        }
+    }
+}
+
+/// texture.extensions[name].source
+fn extension_source(texture: &pi_gltf::Texture, name: &str) -> Option<usize> {
+    let source = texture.extensions()?.get(name)?.get("source")?;
+    source.as_u64().map(|v| v as usize)
+}
+
+/// 按运行时支持的格式为每个 texture 选择图片, 替换之前加载的 gltf 的记录
+/// features 为渲染设备支持的特性, 用于选择 KTX2 转码目标
+pub fn gltf_regist_texture_urls(
+    urls: &mut GLTFTextureURLs,
+    formats: &GLTFImageFormats,
+    features: Features,
+    gltf: &Gltf,
+    root_path: &Path,
+) {
+    let ktx2 = formats.ktx2_target(features);
+    let images = gltf.images().collect::<Vec<_>>();
+    urls.0.clear();
+    for texture in gltf.textures() {
+        let key = texture_key(root_path, texture.index());
+
+        let candidates = [
+            (KHR_TEXTURE_BASISU, ktx2.is_some()),
+            (EXT_TEXTURE_AVIF, formats.avif),
+            (EXT_TEXTURE_WEBP, formats.webp),
+        ];
+        let mut url = None;
+        for (extension, supported) in candidates {
+            if !supported {
+                continue;
+            }
+            if let Some(source) = extension_source(&texture, extension) {
+                match images.get(source) {
+                    Some(img) => {
+                        let img_url = image_url(root_path, img);
+                        url = match ktx2 {
+                            Some(target) if extension == KHR_TEXTURE_BASISU => {
+                                Some(format!("{}#{}", img_url, target.name()))
+                            }
+                            _ => Some(img_url),
+                        };
+                        break;
+                    }
+                    None => log::warn!(
+                        "texture {} {} image {} not found",
+                        texture.index(),
+                        extension,
+                        source
+                    ),
+                }
+            }
+        }
+
+        let url = url.unwrap_or_else(|| image_url(root_path, &texture.source()));
+        urls.0.insert(key, url);
+    }
+}
+
+pub fn gltf_texture_url(
+    urls: &GLTFTextureURLs,
+    root_path: &Path,
+    texture: &pi_gltf::Texture,
+) -> EKeyTexture {
+    let key = texture_key(root_path, texture.index());
+    match urls.0.get(&key) {
+        Some(url) => EKeyTexture::from(url.as_str()),
+        None => EKeyTexture::from(image_url(root_path, &texture.source()).as_str()),
+    }
+}
+
+fn address_mode(mode: WrappingMode) -> EAddressMode {
+    match mode {
+        WrappingMode::ClampToEdge => EAddressMode::ClampToEdge,
+        WrappingMode::MirroredRepeat => EAddressMode::MirrorRepeat,
+        WrappingMode::Repeat => EAddressMode::Repeat,
+    }
+}
+
+pub fn gltf_texture_sampler(texture: &pi_gltf::Texture) -> KeySampler {
+    let sampler = texture.sampler();
+    let mut sample = KeySampler::linear_repeat();
+
+    sample.address_mode_u = address_mode(sampler.wrap_s());
+    sample.address_mode_v = address_mode(sampler.wrap_t());
+
+    if let Some(mag_filter) = sampler.mag_filter() {
+        sample.mag_filter = match mag_filter {
+            MagFilter::Nearest => EFilterMode::Nearest,
+            MagFilter::Linear => EFilterMode::Linear,
+        };
+    }
+
+    if let Some(min_filter) = sampler.min_filter() {
+        let (min_filter, mipmap_filter) = match min_filter {
+            MinFilter::Nearest => (EFilterMode::Nearest, EFilterMode::Nearest),
+            MinFilter::Linear => (EFilterMode::Linear, EFilterMode::Nearest),
+            MinFilter::NearestMipmapNearest => (EFilterMode::Nearest, EFilterMode::Nearest),
+            MinFilter::LinearMipmapNearest => (EFilterMode::Linear, EFilterMode::Nearest),
+            MinFilter::NearestMipmapLinear => (EFilterMode::Nearest, EFilterMode::Linear),
+            MinFilter::LinearMipmapLinear => (EFilterMode::Linear, EFilterMode::Linear),
+        };
+        sample.min_filter = min_filter;
+        sample.mipmap_filter = mipmap_filter;
+    }
+
+    sample
+}
+
+pub fn gltf_texture_param(
+    urls: &GLTFTextureURLs,
+    root_path: &Path,
+    slotname: &str,
+    texture: &pi_gltf::Texture,
+) -> UniformTextureWithSamplerParam {
+    let sample = gltf_texture_sampler(texture);
+    let filter = sample.mag_filter == EFilterMode::Linear
+        || sample.min_filter == EFilterMode::Linear
+        || sample.mipmap_filter == EFilterMode::Linear;
+
+    UniformTextureWithSamplerParam {
+        slotname: Atom::from(slotname),
+        filter,
+        sample,
+        url: gltf_texture_url(urls, root_path, texture),
+    }
+}
+
+fn json_vec2(value: &Value) -> Option<[f32; 2]> {
+    match value.as_array()?.as_slice() {
+        [x, y] => Some([x.as_f64()? as f32, y.as_f64()? as f32]),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GLTFTextureTransform {
+    pub offset: [f32; 2],
+    pub rotation: f32,
+    pub scale: [f32; 2],
+    pub tex_coord: Option<u32>,
+}
+
+impl Default for GLTFTextureTransform {
+    fn default() -> Self {
+        Self {
+            offset: [0., 0.],
+            rotation: 0.,
+            scale: [1., 1.],
+            tex_coord: None,
+        }
+    }
+}
+
+impl GLTFTextureTransform {
+    pub const EXTENSION: &'static str = "KHR_texture_transform";
+
+    /// json 形式的 textureInfo (如 extras 材质中的纹理配置)
+    /// 错误的字段记录 "KHR_texture_transform.key: 原因" 到 errors, 并使用默认值 (不变换)
+    pub fn from_json(info: &Value, errors: &mut Vec<String>) -> Option<Self> {
+        let transform = info.get("extensions")?.get(Self::EXTENSION)?;
+
+        let mut result = Self::default();
+        let mut error = |key: &str, expected: &str, value: &Value| {
+            errors.push(format!(
+                "{}.{}: expected {}, found {}",
+                Self::EXTENSION,
+                key,
+                expected,
+                value
+            ));
+        };
+        if let Some(offset) = transform.get("offset") {
+            match json_vec2(offset) {
+                Some(offset) => result.offset = offset,
+                None => error("offset", "2 numbers", offset),
+            }
+        }
+        if let Some(rotation) = transform.get("rotation") {
+            match rotation.as_f64() {
+                Some(rotation) => result.rotation = rotation as f32,
+                None => error("rotation", "number", rotation),
+            }
+        }
+        if let Some(scale) = transform.get("scale") {
+            match json_vec2(scale) {
+                Some(scale) => result.scale = scale,
+                None => error("scale", "2 numbers", scale),
+            }
+        }
+        if let Some(tex_coord) = transform.get("texCoord") {
+            match tex_coord.as_u64() {
+                Some(tex_coord) => result.tex_coord = Some(tex_coord as u32),
+                None => error("texCoord", "integer", tex_coord),
+            }
+        }
+
+        Some(result)
+    }
+
+    /// 核心材质的 textureInfo
+    pub fn from_info(info: &texture::Info) -> Option<Self> {
+        let transform = info.texture_transform()?;
+        Some(Self {
+            offset: transform.offset(),
+            rotation: transform.rotation(),
+            scale: transform.scale(),
+            tex_coord: transform.tex_coord(),
+        })
+    }
+
+    pub fn tilloff(&self) -> [f32; 4] {
+        [self.scale[0], self.scale[1], self.offset[0], self.offset[1]]
+    }
+
+    /// 纹理块无法表示的配置
+    pub fn check(&self) -> Result<(), String> {
+        if self.rotation != 0. {
+            return Err(format!(
+                "{}.rotation: {} is not supported",
+                Self::EXTENSION,
+                self.rotation
+            ));
+        }
+        match self.tex_coord {
+            Some(tex_coord) if tex_coord != 0 => Err(format!(
+                "{}.texCoord: {} is not supported",
+                Self::EXTENSION,
+                tex_coord
+            )),
+            _ => Ok(()),
+        }
+    }
+}
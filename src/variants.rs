@@ -0,0 +1,100 @@
+/// # KHR_materials_variants
+///   * 加载时创建所有变体引用的材质, 每个 primitive 的 变体 -> 材质 映射记录在 glTF 根节点上
+///   * OpsGLTFSelectVariant 切换变体: 有映射的 mesh 使用变体材质, 其余 mesh 恢复默认材质
+///   * 变体名为空时全部恢复默认材质
+///   * 切换前先恢复 mesh 的 cull / blend / zWrite / zTest / renderQueue, 避免残留上一个材质的状态
+///   * 由 PluginGLTFLoader 注册
+use bevy::prelude::{Component, Entity, Query, ResMut};
+use pi_engine_shell::prelude::*;
+
+use crate::interface::{GLTFCommands, GLTFMeshMaterial, GLTFAPI};
+
+#[derive(Debug, Clone)]
+pub struct GLTFVariantMesh {
+    pub mesh: Entity,
+    /// primitive 的默认材质, 没有材质时使用默认材质
+    pub default: Option<GLTFMeshMaterial>,
+    /// (变体索引列表, 材质)
+    pub mappings: Vec<(Vec<u32>, GLTFMeshMaterial)>,
+}
+
+impl GLTFVariantMesh {
+    pub fn material(&self, variant: Option<u32>) -> Option<&GLTFMeshMaterial> {
+        variant
+            .and_then(|variant| {
+                self.mappings
+                    .iter()
+                    .find(|(variants, _)| variants.contains(&variant))
+                    .map(|(_, material)| material)
+            })
+            .or(self.default.as_ref())
+    }
+}
+
+#[derive(Debug, Clone, Component)]
+pub struct GLTFMaterialVariants {
+    pub scene_id: Entity,
+    pub names: Vec<String>,
+    pub meshes: Vec<GLTFVariantMesh>,
+}
+
+impl GLTFMaterialVariants {
+    pub fn index(&self, name: &str) -> Option<u32> {
+        self.names
+            .iter()
+            .position(|v| v == name)
+            .map(|index| index as u32)
+    }
+}
+
+/// (glTF 根节点, 变体名)
+pub struct OpsGLTFSelectVariant(pub Entity, pub String);
+impl OpsGLTFSelectVariant {
+    pub fn ops(root: Entity, name: &str) -> Self {
+        Self(root, String::from(name))
+    }
+}
+pub type ActionListGLTFSelectVariant = ActionList<OpsGLTFSelectVariant>;
+
+pub fn sys_gltf_select_variant(
+    mut cmds: ResMut<ActionListGLTFSelectVariant>,
+    variants: Query<&GLTFMaterialVariants>,
+    mut cmd: GLTFCommands,
+) {
+    cmds.drain().drain(..).for_each(|OpsGLTFSelectVariant(root, name)| {
+        let variants = match variants.get(root) {
+            Ok(variants) => variants,
+            Err(_) => {
+                log::warn!("gltf {:?} has no material variants", root);
+                return;
+            }
+        };
+
+        let variant = if name.is_empty() {
+            None
+        } else {
+            match variants.index(&name) {
+                Some(index) => Some(index),
+                None => {
+                    log::warn!(
+                        "material variant {} not found, variants: {:?}",
+                        name, variants.names
+                    );
+                    return;
+                }
+            }
+        };
+
+        let mut factory = GLTFAPI {
+            scene_id: variants.scene_id,
+            commands: &mut cmd,
+        };
+        for mesh in variants.meshes.iter() {
+            factory.gltf_reset_render_state(mesh.mesh);
+            match mesh.material(variant) {
+                Some(material) => factory.gltf_apply_material(mesh.mesh, material),
+                None => factory.gltf_default_material(mesh.mesh),
+            }
+        }
+    });
+}